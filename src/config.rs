@@ -19,9 +19,11 @@ use dpp::data_contract::accessors::v0::DataContractV0Getters;
 use ferment_interfaces::{boxed, unbox_any};
 use http::Uri;
 use tokio::runtime::{Builder, Runtime};
+use dpp::dashcore::Network;
+use platform_version::version::PlatformVersion;
 use crate::logs::setup_logs;
 use crate::provider::{Cache, CallbackContextProvider};
-use crate::sdk::DashSdk;
+use crate::sdk::{DashSdk, DashSdkOptions};
 
 pub const TESTNET_ADDRESS_LIST: [&str; 32] = [
     "34.214.48.68",
@@ -73,6 +75,135 @@ pub fn mainnet_address_list() -> Vec<String> {
     MAINNET_ADDRESS_LIST.iter().map(|&s| s.to_string()).collect()
 }
 
+/// Dash network that the SDK connects to
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub enum PlatformNetwork {
+    Mainnet,
+    Testnet,
+    /// A named devnet, such as `devnet-ouzo`
+    Devnet(String),
+    /// A local network created by dashmate, Dash Core uses regtest
+    Regtest,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn PlatformNetwork_clone(value: PlatformNetwork) -> PlatformNetwork {
+    value.clone()
+}
+
+impl Default for PlatformNetwork {
+    fn default() -> Self {
+        PlatformNetwork::Testnet
+    }
+}
+
+impl PlatformNetwork {
+    pub fn from_is_testnet(is_testnet: bool) -> Self {
+        if is_testnet {
+            PlatformNetwork::Testnet
+        } else {
+            PlatformNetwork::Mainnet
+        }
+    }
+
+    /// Network used for Dash Core addresses and keys
+    pub fn core_network(&self) -> Network {
+        match self {
+            PlatformNetwork::Mainnet => Network::Dash,
+            PlatformNetwork::Testnet => Network::Testnet,
+            PlatformNetwork::Devnet(_) => Network::Devnet,
+            PlatformNetwork::Regtest => Network::Regtest,
+        }
+    }
+
+    /// Port of the Dash Platform gateway on the evonodes
+    pub fn default_platform_port(&self) -> u16 {
+        match self {
+            PlatformNetwork::Mainnet => 443,
+            PlatformNetwork::Testnet => 1443,
+            PlatformNetwork::Devnet(_) => 1443,
+            PlatformNetwork::Regtest => 2443,
+        }
+    }
+
+    /// Port of the Dash Core RPC interface
+    pub fn default_core_port(&self) -> u16 {
+        match self {
+            PlatformNetwork::Mainnet => 9998,
+            PlatformNetwork::Testnet => 19998,
+            PlatformNetwork::Devnet(_) => 19798,
+            PlatformNetwork::Regtest => 19898,
+        }
+    }
+
+    /// dashmate does not set up SSL for the gateway of local networks
+    pub fn default_platform_ssl(&self) -> bool {
+        match self {
+            PlatformNetwork::Regtest => false,
+            _ => true,
+        }
+    }
+
+    /// Platform version to use until the network reports its protocol version
+    ///
+    /// This is the version that the [Sdk] uses, the latest one that this library supports.
+    pub fn platform_version(&self) -> &'static PlatformVersion {
+        PlatformVersion::latest()
+    }
+
+    /// Known evonodes of the network, devnets and local networks have none
+    pub fn default_address_list(&self) -> &'static [&'static str] {
+        match self {
+            PlatformNetwork::Mainnet => MAINNET_ADDRESS_LIST.as_slice(),
+            PlatformNetwork::Testnet => TESTNET_ADDRESS_LIST.as_slice(),
+            PlatformNetwork::Devnet(_) | PlatformNetwork::Regtest => &[],
+        }
+    }
+
+    /// Short name of the network, used to name files of the network
+    pub fn name(&self) -> String {
+        match self {
+            PlatformNetwork::Mainnet => "mainnet".to_string(),
+            PlatformNetwork::Testnet => "testnet".to_string(),
+            PlatformNetwork::Devnet(name) => {
+                // the name comes from the app, keep it from escaping the directory of the files
+                let name: String = name.chars()
+                    .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+                    .collect();
+                if name.is_empty() { "devnet".to_string() } else { name }
+            }
+            PlatformNetwork::Regtest => "local".to_string(),
+        }
+    }
+
+    /// Name of the env file with the configuration of the network
+    pub fn env_file(&self) -> String {
+        format!("{}.env", self.name())
+    }
+}
+
+
+/// Add `port` to `host` unless it already has one
+///
+/// Local networks run several evonodes on one host, so the port can be part of the address.
+/// IPv6 addresses are put in brackets.
+fn host_with_port(host: &str, port: u16) -> String {
+    if host.starts_with('[') {
+        if host.contains("]:") {
+            host.to_string()
+        } else {
+            format!("{}:{}", host, port)
+        }
+    } else {
+        match host.matches(':').count() {
+            0 => format!("{}:{}", host, port),
+            1 => host.to_string(),
+            _ => format!("[{}]:{}", host, port),
+        }
+    }
+}
 
 /// Configuration for dash-platform-sdk.
 ///
@@ -110,8 +241,8 @@ pub struct Config {
     /// See [SdkBuilder::with_dump_dir()](crate::SdkBuilder::with_dump_dir()) for more details.
     #[serde(default = "Config::default_dump_dir")]
     pub dump_dir: PathBuf,
-    #[serde(default = "Config::default_is_testnet")]
-    pub is_testnet: bool
+    #[serde(skip)]
+    pub network: PlatformNetwork
 }
 
 pub trait EntryPoint {
@@ -154,33 +285,18 @@ impl Config {
     }
 
     pub fn new_testnet() -> Self {
-        // load config from .env file, ignore errors
-
-        let path: String = env!("CARGO_MANIFEST_DIR").to_owned() + "/testnet.env";
-        if let Err(err) = dotenvy::from_path(&path) {
-            tracing::warn!(path, ?err, "failed to load config file");
-        }
-
-        let mut config: Self = envy::prefixed(Self::CONFIG_PREFIX)
-            .from_env()
-            .expect("configuration error");
-
-        if config.is_empty() {
-            tracing::warn!(path, ?config, "some config fields are empty");
-            config.platform_host = "54.213.204.85".to_string();
-            config.platform_port = 1443;
-            config.core_port = 19998;
-            config.platform_ssl = true;
-            config.is_testnet = true;
-        }
-
-        config
+        Config::new_for_network(PlatformNetwork::Testnet)
     }
 
     pub fn new_mainnet() -> Self {
+        Config::new_for_network(PlatformNetwork::Mainnet)
+    }
+
+    /// Load the configuration of `network` from its env file, see [PlatformNetwork::env_file]
+    pub fn new_for_network(network: PlatformNetwork) -> Self {
         // load config from .env file, ignore errors
 
-        let path: String = env!("CARGO_MANIFEST_DIR").to_owned() + "/mainnet.env";
+        let path: String = env!("CARGO_MANIFEST_DIR").to_owned() + "/" + &network.env_file();
         if let Err(err) = dotenvy::from_path(&path) {
             tracing::warn!(path, ?err, "failed to load config file");
         }
@@ -191,12 +307,16 @@ impl Config {
 
         if config.is_empty() {
             tracing::warn!(path, ?config, "some config fields are empty");
-            config.platform_host = "44.239.39.153".to_string();
-            config.platform_port = 443;
-            config.core_port = 9998;
-            config.platform_ssl = true
+            config.platform_host = match network {
+                PlatformNetwork::Mainnet => "44.239.39.153".to_string(),
+                PlatformNetwork::Testnet => "54.213.204.85".to_string(),
+                _ => "127.0.0.1".to_string(),
+            };
+            config.platform_port = network.default_platform_port();
+            config.core_port = network.default_core_port();
+            config.platform_ssl = network.default_platform_ssl();
         }
-        config.is_testnet = false;
+        config.network = network;
         config
     }
 
@@ -220,11 +340,7 @@ impl Config {
     pub fn address_list(&self) -> AddressList {
         let scheme = if self.platform_ssl { "https" } else { "http" };
 
-        let white_list = if self.is_testnet {
-            TESTNET_ADDRESS_LIST.as_slice()
-        } else {
-            MAINNET_ADDRESS_LIST.as_slice()
-        };
+        let white_list = self.network.default_address_list();
 
         let uris: Result<Vec<http::Uri>, http::uri::InvalidUri> = if white_list.is_empty() {
             tracing::info!("default address list is empty");
@@ -244,8 +360,8 @@ impl Config {
         tracing::info!("new_address_list: {}", address_list.len());
         let scheme = if self.platform_ssl { "https" } else { "http" };
         let uris: Vec<Uri> = address_list.into_iter().map(|host| {
-            let uri = format!("{}://{}:{}", scheme, host, self.platform_port);
             tracing::info!("new_address: {}", host);
+            let uri = format!("{}://{}", scheme, host_with_port(&host, self.platform_port));
             Uri::from_str(&uri).expect("valid address list")
         }).collect();
 
@@ -295,11 +411,10 @@ impl Config {
     }
 
     pub async fn setup_api_with_callbacks(&self, q: u64, d: u64) -> Arc<Sdk> {
-        let mut context_provider = CallbackContextProvider::new(
+        let context_provider = CallbackContextProvider::new(
             std::ptr::null(),
             q,
             d,
-            None,
             Arc::new(Cache::new(NonZeroUsize::new(100).expect("Non Zero"))),
             NonZeroUsize::new(100).expect("Non Zero"),
         ).expect("context provider");
@@ -308,10 +423,12 @@ impl Config {
             let builder = dash_sdk::SdkBuilder::new(self.address_list());
             builder.build().expect("cannot initialize api")
         };
-        // not ideal because context provider has a clone of the sdk
-        context_provider.set_sdk(Some(Arc::new(sdk.clone())));
+        let sdk_reference = context_provider.sdk_reference();
         sdk.set_context_provider(context_provider);
-        sdk.into()
+        let sdk = Arc::new(sdk);
+        // missing data contracts are fetched with the sdk that verifies the proof
+        sdk_reference.set(&sdk);
+        sdk
     }
 
     pub async fn setup_api_with_callbacks_cache(
@@ -321,11 +438,10 @@ impl Config {
         d: u64,
         data_contract_cache: Arc<Cache<Identifier, DataContract>>,
     ) -> Arc<Sdk> {
-        let mut context_provider = CallbackContextProvider::new(
+        let context_provider = CallbackContextProvider::new(
             context_provider_context,
             q,
             d,
-            None,
             data_contract_cache,
             NonZeroUsize::new(100).expect("Non Zero")
         ).expect("context provider");
@@ -334,10 +450,12 @@ impl Config {
             let builder = dash_sdk::SdkBuilder::new(self.address_list());
             builder.build().expect("cannot initialize api")
         };
-        // not ideal because context provider has a clone of the sdk
-        context_provider.set_sdk(Some(Arc::new(sdk.clone())));
+        let sdk_reference = context_provider.sdk_reference();
         sdk.set_context_provider(context_provider);
-        sdk.into()
+        let sdk = Arc::new(sdk);
+        // missing data contracts are fetched with the sdk that verifies the proof
+        sdk_reference.set(&sdk);
+        sdk
     }
 
     pub async fn setup_api_with_callbacks_cache_list(
@@ -348,11 +466,10 @@ impl Config {
         data_contract_cache: Arc<Cache<Identifier, DataContract>>,
         address_list: Vec<String>
     ) -> Arc<Sdk> {
-        let mut context_provider = CallbackContextProvider::new(
+        let context_provider = CallbackContextProvider::new(
             context,
             q,
             d,
-            None,
             data_contract_cache,
             NonZeroUsize::new(100).expect("Non Zero")
         ).expect("context provider");
@@ -361,10 +478,74 @@ impl Config {
             let builder = dash_sdk::SdkBuilder::new(self.new_address_list(address_list));
             builder.build().expect("cannot initialize api")
         };
-        // not ideal because context provider has a clone of the sdk
-        context_provider.set_sdk(Some(Arc::new(sdk.clone())));
+        let sdk_reference = context_provider.sdk_reference();
         sdk.set_context_provider(context_provider);
-        sdk.into()
+        let sdk = Arc::new(sdk);
+        // missing data contracts are fetched with the sdk that verifies the proof
+        sdk_reference.set(&sdk);
+        sdk
+    }
+
+    /// Load the configuration for the network of `options` and apply the
+    /// Dash Core RPC settings that are set in `options`
+    pub fn from_options(options: &DashSdkOptions) -> Self {
+        let mut config = Config::new_for_network(options.network.clone());
+        if !options.core_ip.is_empty() {
+            config.core_ip = options.core_ip.clone();
+        }
+        if options.core_port != 0 {
+            config.core_port = options.core_port;
+        }
+        if !options.core_user.is_empty() {
+            config.core_user = options.core_user.clone();
+        }
+        if !options.core_password.is_empty() {
+            config.core_password = options.core_password.clone();
+        }
+        config
+    }
+
+    /// Create new SDK instance using `options`
+    ///
+    /// Quorum public keys are obtained from the callbacks of `options` when they are set,
+    /// otherwise from Dash Core.
+    pub async fn setup_api_with_options(
+        &self,
+        options: &DashSdkOptions,
+        data_contract_cache: Arc<Cache<Identifier, DataContract>>,
+        quorum_public_keys_cache: Arc<Cache<([u8; 32], u32), [u8; 48]>>
+    ) -> Arc<Sdk> {
+        let address_list = if options.address_list.is_empty() {
+            self.address_list()
+        } else {
+            self.new_address_list(options.address_list.clone())
+        };
+        let builder = dash_sdk::SdkBuilder::new(address_list)
+            .with_settings(options.request_settings());
+
+        if options.quorum_public_key_callback != 0 {
+            let context_provider = CallbackContextProvider::new_with_quorum_public_keys_cache(
+                options.context_provider_context as * const c_void,
+                options.quorum_public_key_callback,
+                options.data_contract_callback,
+                data_contract_cache,
+                quorum_public_keys_cache
+            ).expect("context provider");
+            let sdk_reference = context_provider.sdk_reference();
+            let mut sdk = builder.build().expect("cannot initialize api");
+            sdk.set_context_provider(context_provider);
+            let sdk = Arc::new(sdk);
+            // missing data contracts are fetched with the sdk that verifies the proof
+            sdk_reference.set(&sdk);
+            sdk
+        } else {
+            builder.with_core(
+                &self.core_ip,
+                self.core_port,
+                &self.core_user,
+                &self.core_password,
+            ).build().expect("cannot initialize api").into()
+        }
     }
 
     fn default_dump_dir() -> PathBuf {
@@ -372,10 +553,37 @@ impl Config {
             .join("tests")
             .join("vectors")
     }
-
-    fn default_is_testnet() -> bool {
-        true
-    }
 }
 
 
+
+#[test]
+fn platform_network_test() {
+    assert_eq!(PlatformNetwork::from_is_testnet(true), PlatformNetwork::Testnet);
+    assert_eq!(PlatformNetwork::from_is_testnet(false), PlatformNetwork::Mainnet);
+    assert_eq!(PlatformNetwork::default(), PlatformNetwork::Testnet);
+
+    assert_eq!(PlatformNetwork::Mainnet.core_network(), Network::Dash);
+    assert_eq!(PlatformNetwork::Devnet("devnet-ouzo".to_string()).core_network(), Network::Devnet);
+    assert_eq!(PlatformNetwork::Regtest.core_network(), Network::Regtest);
+    assert_eq!(PlatformNetwork::Testnet.default_platform_port(), 1443);
+    assert!(!PlatformNetwork::Regtest.default_platform_ssl());
+    assert!(PlatformNetwork::Regtest.default_address_list().is_empty());
+    assert_eq!(PlatformNetwork::Mainnet.default_address_list().len(), MAINNET_ADDRESS_LIST.len());
+    assert_eq!(PlatformNetwork::Mainnet.platform_version().protocol_version, PlatformVersion::latest().protocol_version);
+
+    assert_eq!(PlatformNetwork::Testnet.env_file(), "testnet.env");
+    assert_eq!(PlatformNetwork::Regtest.name(), "local");
+    assert_eq!(PlatformNetwork::Devnet("devnet-ouzo".to_string()).name(), "devnet-ouzo");
+    assert_eq!(PlatformNetwork::Devnet("../../etc".to_string()).name(), "______etc");
+    assert_eq!(PlatformNetwork::Devnet("".to_string()).name(), "devnet");
+}
+
+#[test]
+fn host_with_port_test() {
+    assert_eq!(host_with_port("34.214.48.68", 1443), "34.214.48.68:1443");
+    assert_eq!(host_with_port("127.0.0.1:2543", 2443), "127.0.0.1:2543");
+    assert_eq!(host_with_port("2001:db8::1", 1443), "[2001:db8::1]:1443");
+    assert_eq!(host_with_port("[2001:db8::1]", 1443), "[2001:db8::1]:1443");
+    assert_eq!(host_with_port("[2001:db8::1]:2543", 1443), "[2001:db8::1]:2543");
+}
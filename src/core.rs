@@ -5,9 +5,10 @@ use tokio::runtime::Builder;
 use crate::config::{Config, EntryPoint};
 use crate::logs::setup_logs;
 use crate::sdk::DashSdk;
+use crate::error::PlatformError;
 
 #[ferment_macro::export]
-pub fn get_transaction(txid: [u8; 32], quorum_public_key_callback: u64, data_contract_callback: u64) -> Result<Vec<u8>, String> {
+pub fn get_transaction(txid: [u8; 32], quorum_public_key_callback: u64, data_contract_callback: u64) -> Result<Vec<u8>, PlatformError> {
 
     setup_logs();
 
@@ -30,13 +31,13 @@ pub fn get_transaction(txid: [u8; 32], quorum_public_key_callback: u64, data_con
             .await;
         match tx_info_result {
             Ok(tx_info) => Ok(tx_info.transaction),
-            Err(error) => return Err(error.to_string())
+            Err(error) => return Err(dash_sdk::Error::from(error).into())
         }
     })
 }
 
 #[ferment_macro::export]
-pub fn get_transaction_sdk(rust_sdk: * mut DashSdk, txid: [u8; 32]) -> Result<Vec<u8>, String> {
+pub fn get_transaction_sdk(rust_sdk: * mut DashSdk, txid: [u8; 32]) -> Result<Vec<u8>, PlatformError> {
 
     let rt = unsafe { (*rust_sdk).get_runtime() };
 
@@ -54,7 +55,7 @@ pub fn get_transaction_sdk(rust_sdk: * mut DashSdk, txid: [u8; 32]) -> Result<Ve
             .await;
         match tx_info_result {
             Ok(tx_info) => Ok(tx_info.transaction),
-            Err(error) => return Err(error.to_string())
+            Err(error) => return Err(dash_sdk::Error::from(error).into())
         }
     })
 }
\ No newline at end of file
@@ -0,0 +1,303 @@
+use std::str::FromStr;
+use dash_sdk::platform::transition::broadcast_request::BroadcastRequestForStateTransition;
+use dash_sdk::platform::transition::put_settings::PutSettings;
+use dash_sdk::platform::transition::transfer::TransferToIdentity;
+use data_contracts::SystemDataContract;
+use dpp::dashcore::{Address, Network};
+use dpp::document::{Document, DocumentV0Getters};
+use dpp::identity::accessors::IdentityGettersV0;
+use dpp::identity::core_script::CoreScript;
+use dpp::identity::identity::Identity;
+use dpp::identity::identity_public_key::accessors::v0::IdentityPublicKeyGettersV0;
+use dpp::identity::identity_public_key::{IdentityPublicKey, Purpose};
+use dpp::state_transition::identity_credit_withdrawal_transition::IdentityCreditWithdrawalTransition;
+use dpp::state_transition::identity_credit_withdrawal_transition::methods::{IdentityCreditWithdrawalTransitionMethodsV0, PreferredKeyPurposeForSigningWithdrawal};
+use dpp::state_transition::proof_result::StateTransitionProofResult;
+use dpp::withdrawal::Pooling;
+use drive::query::{OrderClause, WhereClause, WhereOperator};
+use platform_value::{Identifier, Value};
+use rs_dapi_client::DapiRequest;
+use tracing::trace;
+use crate::config::EntryPoint;
+use crate::error::PlatformError;
+use crate::fetch_document::{fetch_documents_with_query_and_sdk, sort_documents};
+use crate::put::{CallbackSigner, wait_for_state_transition_result_concurrent};
+use crate::sdk::{create_dash_sdk_using_core_testnet, DashSdk};
+use crate::fetch_identity::test_identifier;
+
+/// Transfer credits from `from_identity` to `to_identity_id`
+///
+/// The transition is signed with `transfer_key`, which must be a `TRANSFER` key of `from_identity`.
+/// Returns the balance of `from_identity` after the transfer.
+#[ferment_macro::export]
+pub fn transfer_credits_sdk(
+    rust_sdk: *mut DashSdk,
+    from_identity: Identity,
+    to_identity_id: Identifier,
+    amount: u64,
+    transfer_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<u64, PlatformError> {
+    if amount == 0 {
+        return Err(PlatformError::InvalidInput("amount must be greater than 0".to_string()));
+    }
+    if from_identity.id() == to_identity_id {
+        return Err(PlatformError::InvalidInput("cannot transfer credits to the same identity".to_string()));
+    }
+    if transfer_key.purpose() != Purpose::TRANSFER {
+        return Err(PlatformError::InvalidInput(format!("key {} is not a transfer key", transfer_key.id())));
+    }
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+
+    rt.block_on(async {
+        let sdk = unsafe { (*rust_sdk).get_sdk() };
+        let request_settings = unsafe { (*rust_sdk).get_request_settings() };
+        let settings = PutSettings {
+            request_settings,
+            identity_nonce_stale_time_s: None,
+            user_fee_increase: None,
+        };
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+
+        trace!("Call Identity::transfer_credits({}, {})", to_identity_id, amount);
+        let balance = from_identity.transfer_credits(
+            &sdk,
+            to_identity_id,
+            amount,
+            Some(&transfer_key),
+            signer,
+            Some(settings)
+        ).await?;
+
+        Ok(balance)
+    })
+}
+
+/// Destination of a credit withdrawal
+#[derive(Clone, Debug)]
+#[ferment_macro::export]
+pub enum WithdrawalOutput {
+    /// A Dash Core address for the network of the SDK
+    Address(String),
+    /// A raw output script
+    Script(Vec<u8>),
+}
+
+impl WithdrawalOutput {
+    fn to_core_script(&self, network: Network) -> Result<CoreScript, PlatformError> {
+        match self {
+            WithdrawalOutput::Address(address) => {
+                let address = Address::from_str(address)
+                    .and_then(|address| address.require_network(network))
+                    .map_err(|e| PlatformError::InvalidInput(format!("invalid address {}: {}", address, e)))?;
+                Ok(CoreScript::new(address.script_pubkey()))
+            }
+            WithdrawalOutput::Script(script) => Ok(CoreScript::from_bytes(script.clone()))
+        }
+    }
+}
+
+/// Withdraw credits from `identity` to Dash Core
+///
+/// The transition is signed with `key`, a `TRANSFER` or `OWNER` key of `identity`.
+/// Returns the balance of `identity` after the withdrawal.
+#[ferment_macro::export]
+pub fn withdraw_credits_sdk(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    amount: u64,
+    core_fee_per_byte: u32,
+    pooling: Pooling,
+    output: WithdrawalOutput,
+    key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<u64, PlatformError> {
+    if amount == 0 {
+        return Err(PlatformError::InvalidInput("amount must be greater than 0".to_string()));
+    }
+    let network = unsafe { (*rust_sdk).get_network() }.core_network();
+    let output_script = output.to_core_script(network)?;
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+
+    rt.block_on(async {
+        let sdk = unsafe { (*rust_sdk).get_sdk() };
+        let request_settings = unsafe { (*rust_sdk).get_request_settings() };
+        let settings = PutSettings {
+            request_settings,
+            identity_nonce_stale_time_s: None,
+            user_fee_increase: None,
+        };
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+
+        let identity_nonce = sdk.get_identity_nonce(identity.id(), true, Some(settings)).await?;
+        trace!("withdraw_credits_sdk: amount {}, nonce {}", amount, identity_nonce);
+
+        let transition = IdentityCreditWithdrawalTransition::try_from_identity(
+            &identity,
+            Some(output_script),
+            amount,
+            pooling,
+            core_fee_per_byte,
+            settings.user_fee_increase.unwrap_or_default(),
+            signer,
+            Some(&key),
+            PreferredKeyPurposeForSigningWithdrawal::TransferPreferred,
+            identity_nonce,
+            sdk.version(),
+            None
+        )?;
+
+        let recorder = unsafe { (*rust_sdk).get_request_recorder() };
+        let request = transition.broadcast_request_for_state_transition()?;
+        request.clone().execute(&sdk, settings.request_settings).await
+            .map_err(|e| {
+                recorder.record_dapi_error(&e);
+                PlatformError::from(e)
+            })?;
+
+        match wait_for_state_transition_result_concurrent(&sdk, &transition, request_settings, unsafe { (*rust_sdk).wait_settings }, &recorder).await? {
+            StateTransitionProofResult::VerifiedPartialIdentity(partial_identity) => {
+                partial_identity.balance.ok_or(PlatformError::ProofVerificationFailed(
+                    "balance is missing from the proof".to_string()
+                ))
+            }
+            result => Err(PlatformError::ProofVerificationFailed(
+                format!("unexpected proof result: {:?}", result)
+            ))
+        }
+    })
+}
+
+/// Status of a `withdrawal` document of the withdrawals contract
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub enum WithdrawalStatus {
+    Queued,
+    Pooled,
+    Broadcasted,
+    Complete,
+    Expired,
+    Unknown,
+}
+
+impl From<u8> for WithdrawalStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => WithdrawalStatus::Queued,
+            1 => WithdrawalStatus::Pooled,
+            2 => WithdrawalStatus::Broadcasted,
+            3 => WithdrawalStatus::Complete,
+            4 => WithdrawalStatus::Expired,
+            _ => WithdrawalStatus::Unknown
+        }
+    }
+}
+
+/// A `withdrawal` document of the withdrawals contract
+#[derive(Clone, Debug)]
+#[ferment_macro::export]
+pub struct WithdrawalInfo {
+    pub id: Identifier,
+    pub owner_id: Identifier,
+    pub amount: u64,
+    pub core_fee_per_byte: u32,
+    pub output_script: Vec<u8>,
+    pub status: WithdrawalStatus,
+    /// Index of the Core transaction, once the withdrawal is pooled
+    pub transaction_index: Option<u64>,
+    pub created_at: Option<u64>,
+    pub updated_at: Option<u64>,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn WithdrawalInfo_clone(value: WithdrawalInfo) -> WithdrawalInfo {
+    value.clone()
+}
+
+impl From<Document> for WithdrawalInfo {
+    fn from(document: Document) -> Self {
+        let properties = document.properties();
+        let integer = |name: &str| properties.get(name).and_then(|value| value.to_integer::<u64>().ok());
+        WithdrawalInfo {
+            id: document.id(),
+            owner_id: document.owner_id(),
+            amount: integer("amount").unwrap_or_default(),
+            core_fee_per_byte: integer("coreFeePerByte").unwrap_or_default() as u32,
+            output_script: properties.get("outputScript")
+                .and_then(|value| value.to_binary_bytes().ok())
+                .unwrap_or_default(),
+            status: WithdrawalStatus::from(integer("status").unwrap_or(u8::MAX as u64) as u8),
+            transaction_index: integer("transactionIndex"),
+            created_at: document.created_at(),
+            updated_at: document.updated_at(),
+        }
+    }
+}
+
+/// List the withdrawals of an identity in the order of the index that the query uses
+///
+/// All withdrawals are returned most recently updated first.  When `only_pending` is true, only
+/// withdrawals that are queued, pooled or broadcasted are returned, broadcasted first and most
+/// recently created first within each status.
+#[ferment_macro::export]
+pub fn fetch_withdrawals_sdk(
+    rust_sdk: *mut DashSdk,
+    identity_id: Identifier,
+    only_pending: bool,
+    limit: u32
+) -> Result<Vec<WithdrawalInfo>, PlatformError> {
+    let owner_clause = WhereClause {
+        field: "$ownerId".into(),
+        value: Value::Identifier(identity_id.to_buffer()),
+        operator: WhereOperator::Equal
+    };
+    let (where_clauses, order_clauses) = if only_pending {
+        // uses the identityStatus index
+        (
+            vec![
+                owner_clause,
+                WhereClause {
+                    field: "status".into(),
+                    value: Value::Array(vec![Value::U8(0), Value::U8(1), Value::U8(2)]),
+                    operator: WhereOperator::In
+                }
+            ],
+            vec![
+                OrderClause { field: "status".into(), ascending: false },
+                OrderClause { field: "$createdAt".into(), ascending: false }
+            ]
+        )
+    } else {
+        // uses the identityRecent index
+        (
+            vec![owner_clause],
+            vec![OrderClause { field: "$updatedAt".into(), ascending: false }]
+        )
+    };
+    let mut documents = fetch_documents_with_query_and_sdk(
+        rust_sdk,
+        SystemDataContract::Withdrawals.id(),
+        "withdrawal".to_string(),
+        where_clauses,
+        order_clauses.clone(),
+        limit,
+        None
+    )?;
+
+    // the documents come back keyed by id, restore the order of the index.  Withdrawals
+    // that are missing an ordered field tie with each other and keep the order of their ids.
+    sort_documents(&mut documents, &order_clauses);
+    Ok(documents.into_iter().map(WithdrawalInfo::from).collect())
+}
+
+#[test]
+fn fetch_withdrawals_sdk_test() {
+    let mut sdk = create_dash_sdk_using_core_testnet();
+    match fetch_withdrawals_sdk(&mut sdk, test_identifier(), false, 100) {
+        Ok(withdrawals) => tracing::info!("withdrawals: {:?}", withdrawals),
+        Err(e) => panic!("{}", e)
+    }
+}
@@ -0,0 +1,702 @@
+use std::collections::{BTreeMap, BTreeSet};
+use dpp::document::{Document, DocumentV0Getters};
+use dpp::identity::accessors::IdentityGettersV0;
+use dpp::identity::identity::Identity;
+use dpp::identity::identity_public_key::accessors::v0::IdentityPublicKeyGettersV0;
+use dpp::identity::identity_public_key::{IdentityPublicKey, KeyType, Purpose};
+use dpp::prelude::CoreBlockHeight;
+use drive::query::{OrderClause, WhereClause, WhereOperator};
+use platform_value::{Identifier, Value};
+use crate::config::EntryPoint;
+use crate::dpns::new_document;
+use crate::encryption::{decrypt_aes_256_cbc, encrypt_aes_256_cbc, encrypted_size};
+use crate::error::PlatformError;
+use crate::fetch_document::{create_document_pager, document_pager_next_page};
+use crate::fetch_identity::{fetch_identities_with_sdk, fetch_identity_with_sdk, IdentityLookup};
+use crate::tx_metadata::get_u32_property;
+use crate::put::{CallbackSigner, put_document_to_platform};
+use crate::request::SdkPtr;
+use crate::sdk::DashSdk;
+
+pub const CONTACT_REQUEST_DOCUMENT_TYPE: &str = "contactRequest";
+/// Size of an extended public key as it is encrypted in a contact request:
+/// parent fingerprint (4), chain code (32) and public key (33)
+pub const CONTACT_XPUB_SIZE: usize = 69;
+/// Size of an extended public key in the BIP32 serialization
+const BIP32_XPUB_SIZE: usize = 78;
+/// `encryptedAccountLabel` must be 48 to 80 bytes, so the label is padded to
+/// at least 16 bytes and can be at most 63 bytes
+const ACCOUNT_LABEL_MIN_SIZE: usize = 16;
+const ACCOUNT_LABEL_MAX_SIZE: usize = 63;
+
+/// Computes the ECDH shared key (DIP-15) of the private key `key_index` of the identity
+/// that owns the callback and the compressed public key `public_key`, and writes the 32 bytes
+/// to `result`.  Returns 0 if the shared key could not be computed.
+pub type SharedKeyCallback = extern "C" fn(context: usize, key_index: u32, public_key: * const u8, public_key_len: u32, result: * mut u8) -> u32;
+
+fn derive_shared_key(
+    key_index: u32,
+    public_key: &IdentityPublicKey,
+    shared_key_context: usize,
+    shared_key_callback: u64
+) -> Result<[u8; 32], PlatformError> {
+    let callback: SharedKeyCallback = unsafe { std::mem::transmute(shared_key_callback as usize) };
+    let public_key_data = public_key.data().as_slice();
+    let mut shared_key = [0u8; 32];
+    if callback(shared_key_context, key_index, public_key_data.as_ptr(), public_key_data.len() as u32, shared_key.as_mut_ptr()) == 0 {
+        return Err(PlatformError::InvalidInput(format!("failed to compute the shared key with key {}", key_index)));
+    }
+    Ok(shared_key)
+}
+
+/// Convert an extended public key to the 69 byte form that is encrypted in a contact request.
+/// Both that form and the 78 byte BIP32 serialization are accepted.
+pub fn contact_xpub_bytes(extended_public_key: &[u8]) -> Result<Vec<u8>, PlatformError> {
+    match extended_public_key.len() {
+        CONTACT_XPUB_SIZE => Ok(extended_public_key.to_vec()),
+        BIP32_XPUB_SIZE => {
+            // version (4), depth (1), parent fingerprint (4), child number (4), chain code (32), public key (33)
+            let mut result = Vec::with_capacity(CONTACT_XPUB_SIZE);
+            result.extend_from_slice(&extended_public_key[5..9]);
+            result.extend_from_slice(&extended_public_key[13..]);
+            Ok(result)
+        }
+        length => Err(PlatformError::InvalidInput(format!("invalid extended public key length: {}", length)))
+    }
+}
+
+fn account_label_bytes(account_label: &str) -> Result<Vec<u8>, PlatformError> {
+    let mut bytes = account_label.as_bytes().to_vec();
+    if bytes.len() > ACCOUNT_LABEL_MAX_SIZE {
+        return Err(PlatformError::InvalidInput(
+            format!("account label must be at most {} bytes: {}", ACCOUNT_LABEL_MAX_SIZE, account_label)
+        ));
+    }
+    // padded with spaces, which are trimmed when the label is decrypted
+    bytes.resize(bytes.len().max(ACCOUNT_LABEL_MIN_SIZE), b' ');
+    Ok(bytes)
+}
+
+// DIP-15 uses an ENCRYPTION key of the sender and a DECRYPTION key of the recipient
+fn get_ecdsa_key(identity: &Identity, key_index: u32, purpose: Purpose) -> Result<IdentityPublicKey, PlatformError> {
+    let key = identity.public_keys()
+        .get(&key_index)
+        .ok_or(PlatformError::InvalidInput(format!("identity {} has no key {}", identity.id(), key_index)))?;
+    if key.key_type() != KeyType::ECDSA_SECP256K1 {
+        return Err(PlatformError::InvalidInput(format!("key {} of identity {} is not an ECDSA key", key_index, identity.id())));
+    }
+    if key.purpose() != purpose {
+        return Err(PlatformError::InvalidInput(format!("key {} of identity {} is not a {:?} key", key_index, identity.id(), purpose)));
+    }
+    if key.disabled_at().is_some() {
+        return Err(PlatformError::InvalidInput(format!("key {} of identity {} is disabled", key_index, identity.id())));
+    }
+    Ok(key.clone())
+}
+
+// the properties of a contact request document, the xpub and the account label are encrypted
+// with the shared key of the sender key and the recipient key
+fn contact_request_properties(
+    sender_identity: &Identity,
+    recipient_identity: &Identity,
+    sender_key_index: u32,
+    recipient_key_index: u32,
+    account_reference: u32,
+    xpub: &[u8],
+    account_label: Option<&[u8]>,
+    shared_key_context: usize,
+    shared_key_callback: u64
+) -> Result<BTreeMap<String, Value>, PlatformError> {
+    get_ecdsa_key(sender_identity, sender_key_index, Purpose::ENCRYPTION)?;
+    let recipient_key = get_ecdsa_key(recipient_identity, recipient_key_index, Purpose::DECRYPTION)?;
+    let shared_key = derive_shared_key(sender_key_index, &recipient_key, shared_key_context, shared_key_callback)?;
+
+    let encrypted_public_key = encrypt_aes_256_cbc(&shared_key, xpub);
+    debug_assert_eq!(encrypted_public_key.len(), encrypted_size(CONTACT_XPUB_SIZE));
+
+    let mut properties: BTreeMap<String, Value> = BTreeMap::new();
+    properties.insert("toUserId".to_string(), Value::Identifier(recipient_identity.id().to_buffer()));
+    properties.insert("encryptedPublicKey".to_string(), Value::Bytes(encrypted_public_key));
+    properties.insert("senderKeyIndex".to_string(), Value::U32(sender_key_index));
+    properties.insert("recipientKeyIndex".to_string(), Value::U32(recipient_key_index));
+    properties.insert("accountReference".to_string(), Value::U32(account_reference));
+    if let Some(account_label) = account_label {
+        properties.insert("encryptedAccountLabel".to_string(), Value::Bytes(encrypt_aes_256_cbc(&shared_key, account_label)));
+    }
+    Ok(properties)
+}
+
+/// Send a DashPay contact request from `sender_identity` to `recipient_identity_id` (DIP-15)
+///
+/// `extended_public_key` is the xpub of the DashPay account that the sender derives for the
+/// recipient, `account_reference` is computed by the wallet from the sender's private key.
+/// The xpub and the optional `account_label` are encrypted with the shared key of the sender key
+/// `sender_key_index`, an ENCRYPTION key, and the recipient key `recipient_key_index`, a
+/// DECRYPTION key, which is computed by `shared_key_callback`.
+#[ferment_macro::export]
+pub fn send_contact_request_sdk(
+    rust_sdk: *mut DashSdk,
+    sender_identity: Identity,
+    recipient_identity_id: Identifier,
+    sender_key_index: u32,
+    recipient_key_index: u32,
+    account_reference: u32,
+    extended_public_key: Vec<u8>,
+    account_label: Option<String>,
+    core_block_height: CoreBlockHeight,
+    shared_key_context: usize,
+    shared_key_callback: u64,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<Document, PlatformError> {
+    if sender_identity.id() == recipient_identity_id {
+        return Err(PlatformError::InvalidInput("cannot send a contact request to the same identity".to_string()));
+    }
+    let xpub = contact_xpub_bytes(&extended_public_key)?;
+    let account_label = match account_label.filter(|label| !label.is_empty()) {
+        Some(label) => Some(account_label_bytes(&label)?),
+        None => None
+    };
+    get_ecdsa_key(&sender_identity, sender_key_index, Purpose::ENCRYPTION)?;
+
+    let recipient_identity = fetch_identity_with_sdk(rust_sdk, recipient_identity_id)?;
+    let properties = contact_request_properties(
+        &sender_identity,
+        &recipient_identity,
+        sender_key_index,
+        recipient_key_index,
+        account_reference,
+        &xpub,
+        account_label.as_deref(),
+        shared_key_context,
+        shared_key_callback
+    )?;
+
+    tracing::info!("send_contact_request_sdk: {} -> {}", sender_identity.id(), recipient_identity_id);
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    rt.block_on(async {
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        put_document_to_platform(
+            SdkPtr(rust_sdk),
+            new_document(sender_identity.id(), properties),
+            Identifier::from(dashpay_contract::ID_BYTES),
+            CONTACT_REQUEST_DOCUMENT_TYPE.to_string(),
+            identity_public_key,
+            0,
+            core_block_height,
+            signer
+        ).await
+    })
+}
+
+/// State of the contact requests between two identities
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub enum ContactStatus {
+    /// Only the identity that is synced sent a contact request
+    PendingOutgoing,
+    /// Only the other identity sent a contact request
+    PendingIncoming,
+    /// Both identities sent a contact request
+    Established,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn ContactStatus_clone(value: ContactStatus) -> ContactStatus {
+    value
+}
+
+/// A DashPay contact of the identity that is synced, as returned by [sync_contacts_sdk]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub struct Contact {
+    /// The other identity
+    pub identity_id: Identifier,
+    pub status: ContactStatus,
+    /// Time of the latest contact request that was sent to the other identity
+    pub sent_at: Option<u64>,
+    /// Time of the latest contact request that was received from the other identity
+    pub received_at: Option<u64>,
+    /// `accountReference` of the received contact request
+    pub account_reference: Option<u32>,
+    /// The decrypted xpub (69 bytes) of the received contact request, used to derive
+    /// the addresses that pay the other identity.  None if it could not be decrypted.
+    pub extended_public_key: Option<Vec<u8>>,
+    /// The decrypted account label of the received contact request
+    pub account_label: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn Contact_clone(value: Contact) -> Contact {
+    value.clone()
+}
+
+// the fields of a contactRequest document that are needed to build a [Contact]
+#[derive(Clone, Debug)]
+struct ContactRequest {
+    owner_id: Identifier,
+    to_user_id: Identifier,
+    created_at: u64,
+    sender_key_index: u32,
+    recipient_key_index: u32,
+    account_reference: u32,
+    encrypted_public_key: Vec<u8>,
+    encrypted_account_label: Option<Vec<u8>>,
+}
+
+impl TryFrom<&Document> for ContactRequest {
+    type Error = PlatformError;
+
+    fn try_from(document: &Document) -> Result<Self, Self::Error> {
+        let properties = document.properties();
+        let to_user_id = properties.get("toUserId")
+            .ok_or(PlatformError::InvalidInput("toUserId is missing".to_string()))?
+            .to_identifier()
+            .map_err(|e| PlatformError::InvalidInput(format!("toUserId is invalid: {}", e)))?;
+        let encrypted_public_key = properties.get("encryptedPublicKey")
+            .ok_or(PlatformError::InvalidInput("encryptedPublicKey is missing".to_string()))?
+            .to_binary_bytes()
+            .map_err(|e| PlatformError::InvalidInput(format!("encryptedPublicKey is invalid: {}", e)))?;
+        Ok(ContactRequest {
+            owner_id: document.owner_id(),
+            to_user_id,
+            created_at: document.created_at().unwrap_or_default(),
+            sender_key_index: get_u32_property(document, "senderKeyIndex")?,
+            recipient_key_index: get_u32_property(document, "recipientKeyIndex")?,
+            account_reference: get_u32_property(document, "accountReference")?,
+            encrypted_public_key,
+            encrypted_account_label: properties.get("encryptedAccountLabel")
+                .and_then(|value| value.to_binary_bytes().ok()),
+        })
+    }
+}
+
+fn fetch_contact_requests(
+    rust_sdk: *mut DashSdk,
+    where_clauses: Vec<WhereClause>,
+    order_clauses: Vec<OrderClause>
+) -> Result<Vec<ContactRequest>, PlatformError> {
+    let mut pager = create_document_pager(
+        rust_sdk,
+        Identifier::from(dashpay_contract::ID_BYTES),
+        CONTACT_REQUEST_DOCUMENT_TYPE.to_string(),
+        where_clauses,
+        order_clauses,
+        100
+    )?;
+    let mut contact_requests = vec![];
+    while !pager.is_exhausted() {
+        for document in document_pager_next_page(rust_sdk, &mut pager)? {
+            match ContactRequest::try_from(&document) {
+                Ok(contact_request) => contact_requests.push(contact_request),
+                Err(e) => tracing::warn!("fetch_contact_requests: skipping document {}: {}", document.id(), e)
+            }
+        }
+    }
+    Ok(contact_requests)
+}
+
+// decrypt the xpub and account label of a contact request that was received from `sender`
+fn decrypt_contact_request(
+    contact_request: &ContactRequest,
+    sender: &Identity,
+    decrypt_context: usize,
+    decrypt_callback: u64
+) -> Result<(Vec<u8>, Option<String>), PlatformError> {
+    let sender_key = get_ecdsa_key(sender, contact_request.sender_key_index, Purpose::ENCRYPTION)?;
+    let shared_key = derive_shared_key(contact_request.recipient_key_index, &sender_key, decrypt_context, decrypt_callback)?;
+    let extended_public_key = decrypt_aes_256_cbc(&shared_key, &contact_request.encrypted_public_key)?;
+    if extended_public_key.len() != CONTACT_XPUB_SIZE {
+        return Err(PlatformError::InvalidInput(format!("invalid extended public key length: {}", extended_public_key.len())));
+    }
+    let account_label = match &contact_request.encrypted_account_label {
+        Some(encrypted) => {
+            let label = decrypt_aes_256_cbc(&shared_key, encrypted)?;
+            Some(String::from_utf8_lossy(&label).trim_end().to_string())
+        }
+        None => None
+    };
+    Ok((extended_public_key, account_label))
+}
+
+/// Platform accepts at most 100 values in an `in` clause
+const MAX_IN_VALUES: usize = 100;
+
+// the contact requests between the identity that is synced and one other identity
+#[derive(Debug)]
+struct ContactPair {
+    user_id: Identifier,
+    /// The latest request that was sent to the other identity
+    sent: Option<ContactRequest>,
+    /// The latest request that was received from the other identity
+    received: Option<ContactRequest>,
+}
+
+impl ContactPair {
+    fn status(&self) -> ContactStatus {
+        match (&self.sent, &self.received) {
+            (Some(_), Some(_)) => ContactStatus::Established,
+            (Some(_), None) => ContactStatus::PendingOutgoing,
+            _ => ContactStatus::PendingIncoming,
+        }
+    }
+}
+
+// pair the requests that `identity_id` sent and received by the other identity, keeping the
+// latest request of each direction, and return the pairs with a request created after `since_ms`
+fn pair_contact_requests(
+    identity_id: Identifier,
+    since_ms: u64,
+    contact_requests: Vec<ContactRequest>
+) -> Vec<ContactPair> {
+    let mut pairs: BTreeMap<Identifier, ContactPair> = BTreeMap::new();
+    for contact_request in contact_requests {
+        let (user_id, sent) = if contact_request.owner_id == identity_id {
+            (contact_request.to_user_id, true)
+        } else if contact_request.to_user_id == identity_id {
+            (contact_request.owner_id, false)
+        } else {
+            continue;
+        };
+        let pair = pairs.entry(user_id).or_insert(ContactPair { user_id, sent: None, received: None });
+        let latest = if sent { &mut pair.sent } else { &mut pair.received };
+        if latest.as_ref().map_or(true, |latest| contact_request.created_at > latest.created_at) {
+            *latest = Some(contact_request);
+        }
+    }
+    pairs.into_values()
+        .filter(|pair| [&pair.sent, &pair.received].iter()
+            .any(|request| request.as_ref().map_or(false, |request| request.created_at > since_ms)))
+        .collect()
+}
+
+// the users of `user_ids` that are not in `answered`, sorted and without duplicates
+fn unanswered_user_ids(
+    user_ids: impl Iterator<Item = Identifier>,
+    answered: impl Iterator<Item = Identifier>
+) -> Vec<Identifier> {
+    let answered: BTreeSet<Identifier> = answered.collect();
+    user_ids.filter(|user_id| !answered.contains(user_id))
+        .collect::<BTreeSet<Identifier>>()
+        .into_iter()
+        .collect()
+}
+
+// the where and order clauses that fetch the contact requests between `identity_id` and
+// `user_ids` in the direction given by `sent`
+fn contact_requests_with_users_query(
+    identity_id: Identifier,
+    user_ids: &[Identifier],
+    sent: bool
+) -> (Vec<WhereClause>, Vec<OrderClause>) {
+    if sent {
+        // uses the ownerIdUserId index
+        let values = user_ids.iter().map(|user_id| Value::Identifier(user_id.to_buffer())).collect();
+        (
+            vec![
+                WhereClause { field: "$ownerId".into(), value: Value::Identifier(identity_id.to_buffer()), operator: WhereOperator::Equal },
+                WhereClause { field: "toUserId".into(), value: Value::Array(values), operator: WhereOperator::In }
+            ],
+            vec![
+                OrderClause { field: "toUserId".into(), ascending: true }
+            ]
+        )
+    } else {
+        // there is no index on toUserId and $ownerId, so the userIdCreatedAt index is paged
+        // and the senders are filtered by the caller
+        (
+            vec![
+                WhereClause { field: "toUserId".into(), value: Value::Identifier(identity_id.to_buffer()), operator: WhereOperator::Equal }
+            ],
+            vec![
+                OrderClause { field: "$createdAt".into(), ascending: true }
+            ]
+        )
+    }
+}
+
+// fetch the contact requests between `identity_id` and `user_ids`, in the direction given by
+// `sent`.  Sent requests take one paged `in` query for every 100 users, received requests
+// take one paged query that is filtered by sender.
+fn fetch_contact_requests_with_users(
+    rust_sdk: *mut DashSdk,
+    identity_id: Identifier,
+    user_ids: Vec<Identifier>,
+    sent: bool
+) -> Result<Vec<ContactRequest>, PlatformError> {
+    if user_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    if !sent {
+        let (where_clauses, order_clauses) = contact_requests_with_users_query(identity_id, &user_ids, sent);
+        let senders: BTreeSet<Identifier> = user_ids.into_iter().collect();
+        return Ok(fetch_contact_requests(rust_sdk, where_clauses, order_clauses)?
+            .into_iter()
+            .filter(|contact_request| senders.contains(&contact_request.owner_id))
+            .collect());
+    }
+    let mut contact_requests = vec![];
+    for user_ids in user_ids.chunks(MAX_IN_VALUES) {
+        let (where_clauses, order_clauses) = contact_requests_with_users_query(identity_id, user_ids, sent);
+        contact_requests.extend(fetch_contact_requests(rust_sdk, where_clauses, order_clauses)?);
+    }
+    Ok(contact_requests)
+}
+
+/// Get the DashPay contacts of `identity` that have changed since `since_ms`
+///
+/// The contact requests that `identity` sent and received after `since_ms` are fetched.  When
+/// one of them answers an older request, the older request is fetched too, so that the contact
+/// is [ContactStatus::Established].  The xpubs of received requests are decrypted with the
+/// shared key that `decrypt_callback`, a [SharedKeyCallback] for the keys of `identity`, computes.
+#[ferment_macro::export]
+pub fn sync_contacts_sdk(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    since_ms: u64,
+    decrypt_context: usize,
+    decrypt_callback: u64
+) -> Result<Vec<Contact>, PlatformError> {
+    let identity_id = identity.id();
+    let since = |field: &str| vec![
+        WhereClause { field: field.into(), value: Value::Identifier(identity_id.to_buffer()), operator: WhereOperator::Equal },
+        WhereClause { field: "$createdAt".into(), value: Value::U64(since_ms), operator: WhereOperator::GreaterThan }
+    ];
+    let created_at_order = || vec![OrderClause { field: "$createdAt".into(), ascending: true }];
+    // uses the ownerIdCreatedAt index
+    let sent = fetch_contact_requests(rust_sdk, since("$ownerId"), created_at_order())?;
+    // uses the userIdCreatedAt index
+    let received = fetch_contact_requests(rust_sdk, since("toUserId"), created_at_order())?;
+
+    // a request from after `since_ms` may answer a request from before, which is needed to pair them
+    let older_received = fetch_contact_requests_with_users(
+        rust_sdk,
+        identity_id,
+        unanswered_user_ids(sent.iter().map(|request| request.to_user_id), received.iter().map(|request| request.owner_id)),
+        false
+    )?;
+    let older_sent = fetch_contact_requests_with_users(
+        rust_sdk,
+        identity_id,
+        unanswered_user_ids(received.iter().map(|request| request.owner_id), sent.iter().map(|request| request.to_user_id)),
+        true
+    )?;
+
+    let pairs = pair_contact_requests(
+        identity_id,
+        since_ms,
+        sent.into_iter().chain(received).chain(older_received).chain(older_sent).collect()
+    );
+
+    // the senders are needed to decrypt the received requests
+    let senders = fetch_identities_with_sdk(
+        rust_sdk,
+        pairs.iter().filter(|pair| pair.received.is_some()).map(|pair| pair.user_id).collect()
+    );
+
+    let mut contacts = vec![];
+    for pair in pairs {
+        let (extended_public_key, account_label) = match (&pair.received, senders.get(&pair.user_id)) {
+            (Some(contact_request), Some(IdentityLookup::Found(sender))) => {
+                match decrypt_contact_request(contact_request, sender, decrypt_context, decrypt_callback) {
+                    Ok((extended_public_key, account_label)) => (Some(extended_public_key), account_label),
+                    Err(e) => {
+                        tracing::warn!("sync_contacts_sdk: cannot decrypt the contact request from {}: {}", pair.user_id, e);
+                        (None, None)
+                    }
+                }
+            }
+            _ => (None, None)
+        };
+        contacts.push(Contact {
+            identity_id: pair.user_id,
+            status: pair.status(),
+            sent_at: pair.sent.as_ref().map(|request| request.created_at),
+            received_at: pair.received.as_ref().map(|request| request.created_at),
+            account_reference: pair.received.as_ref().map(|request| request.account_reference),
+            extended_public_key,
+            account_label,
+        });
+    }
+    tracing::info!("sync_contacts_sdk: {} contacts changed since {}", contacts.len(), since_ms);
+    Ok(contacts)
+}
+
+#[test]
+fn contact_request_encoding_test() {
+    let bip32_xpub: Vec<u8> = (0..BIP32_XPUB_SIZE as u8).collect();
+    let xpub = contact_xpub_bytes(&bip32_xpub).unwrap();
+    assert_eq!(xpub.len(), CONTACT_XPUB_SIZE);
+    assert_eq!(xpub[..4], [5, 6, 7, 8]);
+    assert_eq!(xpub[4], 13);
+    assert_eq!(contact_xpub_bytes(&xpub).unwrap(), xpub);
+    assert!(contact_xpub_bytes(&[0u8; 33]).is_err());
+    // encryptedPublicKey must be exactly 96 bytes
+    assert_eq!(encrypted_size(CONTACT_XPUB_SIZE), 96);
+
+    // encryptedAccountLabel must be 48 to 80 bytes
+    assert_eq!(encrypted_size(account_label_bytes("Bob").unwrap().len()), 48);
+    assert_eq!(encrypted_size(account_label_bytes(&"a".repeat(ACCOUNT_LABEL_MAX_SIZE)).unwrap().len()), 80);
+    assert!(account_label_bytes(&"a".repeat(ACCOUNT_LABEL_MAX_SIZE + 1)).is_err());
+}
+
+#[test]
+fn contact_request_from_document_test() {
+    let owner_id = Identifier::from([1u8; 32]);
+    let to_user_id = Identifier::from([2u8; 32]);
+    let mut properties: BTreeMap<String, Value> = BTreeMap::new();
+    properties.insert("toUserId".to_string(), Value::Identifier(to_user_id.to_buffer()));
+    properties.insert("encryptedPublicKey".to_string(), Value::Bytes(vec![0u8; 96]));
+    properties.insert("senderKeyIndex".to_string(), Value::U32(1));
+    properties.insert("recipientKeyIndex".to_string(), Value::U32(2));
+    properties.insert("accountReference".to_string(), Value::U32(3));
+    let contact_request = ContactRequest::try_from(&new_document(owner_id, properties.clone())).unwrap();
+    assert_eq!(contact_request.owner_id, owner_id);
+    assert_eq!(contact_request.to_user_id, to_user_id);
+    assert_eq!(contact_request.sender_key_index, 1);
+    assert_eq!(contact_request.recipient_key_index, 2);
+    assert_eq!(contact_request.account_reference, 3);
+    assert_eq!(contact_request.encrypted_public_key.len(), 96);
+    assert!(contact_request.encrypted_account_label.is_none());
+
+    properties.remove("toUserId");
+    assert!(ContactRequest::try_from(&new_document(owner_id, properties)).is_err());
+}
+
+#[cfg(test)]
+fn test_dashpay_identity(id: u8, purpose: Purpose) -> Identity {
+    use dpp::identity::identity_public_key::SecurityLevel;
+    use dpp::identity::identity_public_key::v0::IdentityPublicKeyV0;
+    use dpp::identity::v0::IdentityV0;
+    use platform_value::types::binary_data::BinaryData;
+
+    let key = IdentityPublicKey::V0(IdentityPublicKeyV0 {
+        id: 1,
+        purpose,
+        security_level: SecurityLevel::MEDIUM,
+        contract_bounds: None,
+        key_type: KeyType::ECDSA_SECP256K1,
+        read_only: false,
+        data: BinaryData::new(vec![id; 33]),
+        disabled_at: None,
+    });
+    Identity::V0(IdentityV0 {
+        id: Identifier::from([id; 32]),
+        public_keys: BTreeMap::from([(1, key)]),
+        balance: 0,
+        revision: 0,
+    })
+}
+
+#[test]
+fn contact_request_encryption_test() {
+    // both sides compute the same ECDH shared key, a fixed key stands in for it
+    extern "C" fn fixed_shared_key(_context: usize, _key_index: u32, _public_key: * const u8, _public_key_len: u32, result: * mut u8) -> u32 {
+        unsafe { std::ptr::copy_nonoverlapping([9u8; 32].as_ptr(), result, 32) };
+        1
+    }
+    let sender = test_dashpay_identity(1, Purpose::ENCRYPTION);
+    let recipient = test_dashpay_identity(2, Purpose::DECRYPTION);
+    let xpub: Vec<u8> = (0..CONTACT_XPUB_SIZE as u8).collect();
+    let account_label = account_label_bytes("Bob").unwrap();
+
+    let properties = contact_request_properties(
+        &sender, &recipient, 1, 1, 7, &xpub, Some(&account_label), 0, fixed_shared_key as u64
+    ).unwrap();
+    let contact_request = ContactRequest::try_from(&new_document(sender.id(), properties)).unwrap();
+    assert_eq!(contact_request.to_user_id, recipient.id());
+    assert_eq!(contact_request.encrypted_public_key.len(), encrypted_size(CONTACT_XPUB_SIZE));
+
+    let (decrypted_xpub, decrypted_label) = decrypt_contact_request(&contact_request, &sender, 0, fixed_shared_key as u64).unwrap();
+    assert_eq!(decrypted_xpub, xpub);
+    assert_eq!(decrypted_label, Some("Bob".to_string()));
+
+    // the sender key must be an ENCRYPTION key and the recipient key a DECRYPTION key
+    assert!(contact_request_properties(&recipient, &sender, 1, 1, 7, &xpub, None, 0, fixed_shared_key as u64).is_err());
+    assert!(decrypt_contact_request(&contact_request, &recipient, 0, fixed_shared_key as u64).is_err());
+}
+
+#[test]
+fn pair_contact_requests_test() {
+    let identity_id = Identifier::from([1u8; 32]);
+    let contact_request = |owner_id: u8, to_user_id: u8, created_at: u64| ContactRequest {
+        owner_id: Identifier::from([owner_id; 32]),
+        to_user_id: Identifier::from([to_user_id; 32]),
+        created_at,
+        sender_key_index: 0,
+        recipient_key_index: 0,
+        account_reference: created_at as u32,
+        encrypted_public_key: vec![],
+        encrypted_account_label: None,
+    };
+    let since_ms = 1000;
+    let pairs = pair_contact_requests(identity_id, since_ms, vec![
+        // both sent after since_ms
+        contact_request(1, 2, 1100),
+        contact_request(2, 1, 1200),
+        // sent after since_ms, answers a request received before
+        contact_request(1, 3, 1300),
+        contact_request(3, 1, 500),
+        // only sent
+        contact_request(1, 4, 1400),
+        // only received, twice, the latest is kept
+        contact_request(5, 1, 1500),
+        contact_request(5, 1, 1600),
+        // both before since_ms
+        contact_request(1, 6, 100),
+        contact_request(6, 1, 200),
+        // between other identities
+        contact_request(7, 8, 1700),
+    ]);
+
+    let statuses: Vec<(u8, ContactStatus)> = pairs.iter()
+        .map(|pair| (pair.user_id.to_buffer()[0], pair.status()))
+        .collect();
+    assert_eq!(statuses, vec![
+        (2, ContactStatus::Established),
+        (3, ContactStatus::Established),
+        (4, ContactStatus::PendingOutgoing),
+        (5, ContactStatus::PendingIncoming),
+    ]);
+    assert_eq!(pairs[1].received.as_ref().unwrap().created_at, 500);
+    assert_eq!(pairs[3].received.as_ref().unwrap().created_at, 1600);
+    assert!(pairs[3].sent.is_none());
+    assert_eq!(
+        unanswered_user_ids(
+            [Identifier::from([3u8; 32]), Identifier::from([2u8; 32]), Identifier::from([3u8; 32])].into_iter(),
+            [Identifier::from([2u8; 32])].into_iter()
+        ),
+        vec![Identifier::from([3u8; 32])]
+    );
+}
+
+#[test]
+fn contact_requests_with_users_query_test() {
+    let identity_id = Identifier::from([1u8; 32]);
+    let user_ids = vec![Identifier::from([2u8; 32]), Identifier::from([3u8; 32])];
+    let fields = |(where_clauses, order_clauses): (Vec<WhereClause>, Vec<OrderClause>)| (
+        where_clauses.into_iter().map(|clause| (clause.field, clause.operator, clause.value)).collect::<Vec<_>>(),
+        order_clauses.into_iter().map(|clause| (clause.field, clause.ascending)).collect::<Vec<_>>()
+    );
+
+    // ownerIdUserId index: ($ownerId, toUserId)
+    let (where_clauses, order_clauses) = fields(contact_requests_with_users_query(identity_id, &user_ids, true));
+    assert_eq!(where_clauses, vec![
+        ("$ownerId".to_string(), WhereOperator::Equal, Value::Identifier(identity_id.to_buffer())),
+        ("toUserId".to_string(), WhereOperator::In, Value::Array(vec![
+            Value::Identifier(user_ids[0].to_buffer()),
+            Value::Identifier(user_ids[1].to_buffer())
+        ])),
+    ]);
+    assert_eq!(order_clauses, vec![("toUserId".to_string(), true)]);
+
+    // userIdCreatedAt index: (toUserId, $createdAt)
+    let (where_clauses, order_clauses) = fields(contact_requests_with_users_query(identity_id, &user_ids, false));
+    assert_eq!(where_clauses, vec![
+        ("toUserId".to_string(), WhereOperator::Equal, Value::Identifier(identity_id.to_buffer())),
+    ]);
+    assert_eq!(order_clauses, vec![("$createdAt".to_string(), true)]);
+}
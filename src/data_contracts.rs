@@ -5,16 +5,20 @@ use dash_sdk::{Error, Sdk};
 use dpp::data_contract::accessors::v0::DataContractV0Getters;
 use dpp::data_contract::DataContract;
 use dpp::document::Document;
+use dpp::system_data_contracts::load_system_data_contract;
+use data_contracts::SystemDataContract;
 use drive_proof_verifier::types::Documents;
 use platform_value::{Identifier, IdentifierBytes32};
 use platform_value::string_encoding::Encoding;
+use platform_version::version::PlatformVersion;
 use rs_dapi_client::RequestSettings;
 use rs_dapi_client::transport::BoxFuture;
 use tokio::runtime::Builder;
-use crate::config::{Config, EntryPoint};
+use crate::config::{Config, EntryPoint, PlatformNetwork};
 use crate::logs::setup_logs;
 use crate::provider::Cache;
 use crate::sdk::{create_dash_sdk_using_core_testnet, DashSdk};
+use crate::error::PlatformError;
 
 #[derive(Clone, Debug)]
 #[ferment_macro::export]
@@ -58,7 +62,7 @@ fn fetch_data_contract_with_retry(
     data_contract_id: Identifier,
     request_settings: RequestSettings,
     retries_left: usize,
-) -> BoxFuture<'static, Result<Option<DataContract>, Error>> {
+) -> BoxFuture<'static, Result<Option<DataContract>, PlatformError>> {
     Box::pin(async move {
         match DataContract::fetch_with_settings(&sdk, data_contract_id.clone(), request_settings).await {
             Ok(Some(data_contract)) => Ok(Some(data_contract)),
@@ -72,8 +76,9 @@ fn fetch_data_contract_with_retry(
                 Ok(None)
             }
             Err(error) => {
+                let error = PlatformError::from(error);
                 if retries_left > 1 {
-                    if error.to_string().contains("contract not found error") {
+                    if let PlatformError::DataContractNotFound(_) = error {
                         if data_contract_id == Identifier::from(dpns_contract::ID_BYTES) ||
                             data_contract_id == Identifier::from(dashpay_contract::ID_BYTES) {
                             return fetch_data_contract_with_retry(sdk, data_contract_id, request_settings, retries_left - 1).await;
@@ -86,11 +91,40 @@ fn fetch_data_contract_with_retry(
     })
 }
 
+/// System data contracts are created at genesis with the same ids on every network
+const SYSTEM_DATA_CONTRACTS: [SystemDataContract; 5] = [
+    SystemDataContract::Withdrawals,
+    SystemDataContract::MasternodeRewards,
+    SystemDataContract::FeatureFlags,
+    SystemDataContract::DPNS,
+    SystemDataContract::Dashpay,
+];
+
+/// Add the compiled-in system data contracts to `data_contract_cache`, so that documents of
+/// these contracts can be verified without fetching the contracts.  Contracts that are already
+/// in the cache, such as those fetched from Platform in a previous session, are kept.
+/// `platform_version` should be the version that the network runs.
+pub fn preload_system_data_contracts(
+    data_contract_cache: &Cache<Identifier, DataContract>,
+    platform_version: &PlatformVersion
+) {
+    for system_data_contract in SYSTEM_DATA_CONTRACTS {
+        let id = system_data_contract.id();
+        if data_contract_cache.get(&id).is_some() {
+            continue;
+        }
+        match load_system_data_contract(system_data_contract, platform_version) {
+            Ok(data_contract) => data_contract_cache.put(id, data_contract),
+            Err(e) => tracing::warn!("preload_system_data_contracts: cannot load {:?}: {}", system_data_contract, e)
+        }
+    }
+}
+
 #[ferment_macro::export]
 pub fn fetch_data_contract(
     rust_sdk: *mut DashSdk,
     data_contract_id: Identifier
-) -> Result<Option<DataContractFFI>, String> {
+) -> Result<Option<DataContractFFI>, PlatformError> {
     let rt = unsafe { (*rust_sdk).get_runtime() };
 
     // Execute the async block using the Tokio runtime
@@ -110,7 +144,7 @@ pub fn fetch_data_contract(
                         Ok(Some(data_contract_ffi))
                     },
                     Ok(None) => return Ok(None),//Err("data contract not found".to_string()),
-                    Err(e) => return Err(e.to_string())
+                    Err(e) => return Err(e.into())
                 }
             }
         }
@@ -136,4 +170,14 @@ fn get_missing_data_contract_test() {
     );
 
     assert!(data_contract_result.is_ok());
-}
\ No newline at end of file
+}
+#[test]
+fn preload_system_data_contracts_test() {
+    use std::num::NonZeroUsize;
+
+    let cache = Cache::new(NonZeroUsize::new(10).unwrap());
+    preload_system_data_contracts(&cache, PlatformNetwork::Mainnet.platform_version());
+    let dpns = cache.get(&Identifier::from(dpns_contract::ID_BYTES)).expect("dpns contract");
+    assert!(dpns.document_type_for_name("domain").is_ok());
+    assert!(cache.get(&Identifier::from(dashpay_contract::ID_BYTES)).is_some());
+}
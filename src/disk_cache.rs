@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use dpp::data_contract::DataContract;
+use dpp::serialization::{PlatformDeserializableWithPotentialValidationFromVersionedStructure, PlatformSerializableWithPlatformVersion};
+use platform_value::Identifier;
+use platform_value::string_encoding::Encoding;
+use parking_lot::RwLock;
+use platform_version::version::PlatformVersion;
+use crate::config::PlatformNetwork;
+use crate::provider::CacheStore;
+
+/// Version of the layout of the files in the disk cache, increase it when the layout changes
+const DISK_CACHE_FORMAT_VERSION: u32 = 1;
+/// File in the directory of a network with the protocol version that the network reported last
+const PROTOCOL_VERSION_FILE: &str = "protocol-version";
+
+/// Stores data contracts and quorum public keys in `cache_dir` so that they survive restarts
+///
+/// Entries are kept in a directory named after the cache format version and the protocol
+/// version that the network reported, which is also used to serialize them.  When either
+/// of these change, the entries of the previous versions are removed.
+pub struct DiskCache {
+    network_dir: PathBuf,
+    current: RwLock<DiskCacheVersion>,
+}
+
+struct DiskCacheVersion {
+    dir: PathBuf,
+    platform_version: &'static PlatformVersion,
+}
+
+impl DiskCache {
+    /// Open the disk cache of `network` in `cache_dir`, returns None if `cache_dir` is empty
+    ///
+    /// The protocol version that the network reported last is used until
+    /// [DiskCache::set_protocol_version] is called, or `default_version` if there is none.
+    pub fn open(
+        cache_dir: &str,
+        network: &PlatformNetwork,
+        default_version: &'static PlatformVersion
+    ) -> Option<Arc<DiskCache>> {
+        if cache_dir.is_empty() {
+            return None;
+        }
+        let network_dir = PathBuf::from(cache_dir).join(format!("cache-{}", network.name()));
+        let platform_version = fs::read_to_string(network_dir.join(PROTOCOL_VERSION_FILE)).ok()
+            .and_then(|text| text.trim().parse::<u32>().ok())
+            .and_then(|protocol_version| PlatformVersion::get(protocol_version).ok())
+            .unwrap_or(default_version);
+        let current = open_version(&network_dir, platform_version)?;
+        Some(Arc::new(DiskCache { network_dir, current: RwLock::new(current) }))
+    }
+
+    /// Use the entries of `protocol_version`, as reported by the network, from now on
+    pub fn set_protocol_version(&self, protocol_version: u32) {
+        if self.current.read().platform_version.protocol_version == protocol_version {
+            return;
+        }
+        let platform_version = match PlatformVersion::get(protocol_version) {
+            Ok(platform_version) => platform_version,
+            Err(e) => {
+                tracing::warn!("DiskCache: unknown protocol version {}: {}", protocol_version, e);
+                return;
+            }
+        };
+        let mut current = self.current.write();
+        if let Some(version) = open_version(&self.network_dir, platform_version) {
+            tracing::info!("DiskCache: using protocol version {}", protocol_version);
+            write_file(&self.network_dir.join(PROTOCOL_VERSION_FILE), protocol_version.to_string().as_bytes());
+            *current = version;
+        }
+    }
+
+    fn data_contract_path(&self, id: &Identifier) -> PathBuf {
+        self.current.read().dir.join("contracts").join(format!("{}.bin", id.to_string(Encoding::Hex)))
+    }
+
+    fn quorum_public_key_path(&self, quorum_hash: &[u8; 32], quorum_type: u32) -> PathBuf {
+        self.current.read().dir.join("quorums").join(format!("{}-{}.bin", quorum_type, hex::encode(quorum_hash)))
+    }
+
+    /// Platform version of the entries that are read and written
+    pub fn platform_version(&self) -> &'static PlatformVersion {
+        self.current.read().platform_version
+    }
+}
+
+fn open_version(network_dir: &PathBuf, platform_version: &'static PlatformVersion) -> Option<DiskCacheVersion> {
+    let version = format!("v{}-p{}", DISK_CACHE_FORMAT_VERSION, platform_version.protocol_version);
+    remove_other_versions(network_dir, &version);
+    let dir = network_dir.join(version);
+    for sub_dir in ["contracts", "quorums"] {
+        if let Err(e) = fs::create_dir_all(dir.join(sub_dir)) {
+            tracing::warn!("DiskCache: cannot create {:?}: {}", dir.join(sub_dir), e);
+            return None;
+        }
+    }
+    Some(DiskCacheVersion { dir, platform_version })
+}
+
+fn remove_other_versions(network_dir: &PathBuf, version: &str) {
+    let entries = match fs::read_dir(network_dir) {
+        Ok(entries) => entries,
+        Err(_) => return
+    };
+    for entry in entries.flatten() {
+        if entry.file_name() != version && entry.path().is_dir() {
+            tracing::info!("DiskCache: removing stale entries {:?}", entry.path());
+            if let Err(e) = fs::remove_dir_all(entry.path()) {
+                tracing::warn!("DiskCache: cannot remove {:?}: {}", entry.path(), e);
+            }
+        }
+    }
+}
+
+/// A temporary file next to `path` that no other write uses, also from other processes
+pub(crate) fn unique_temp_path(path: &PathBuf) -> PathBuf {
+    static NEXT_TEMP_FILE: AtomicU64 = AtomicU64::new(0);
+    path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        NEXT_TEMP_FILE.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+fn write_file(path: &PathBuf, bytes: &[u8]) {
+    // write to a temporary file first so that a crash does not leave a partial entry,
+    // each write has its own temporary file since entries can be stored concurrently
+    let temp_path = unique_temp_path(path);
+    if let Err(e) = fs::write(&temp_path, bytes).and_then(|_| fs::rename(&temp_path, path)) {
+        tracing::warn!("DiskCache: cannot write {:?}: {}", path, e);
+        let _ = fs::remove_file(&temp_path);
+    }
+}
+
+impl CacheStore<Identifier, DataContract> for DiskCache {
+    fn load(&self, id: &Identifier) -> Option<DataContract> {
+        let path = self.data_contract_path(id);
+        let bytes = fs::read(&path).ok()?;
+        match DataContract::versioned_deserialize(&bytes, false, self.platform_version()) {
+            Ok(data_contract) => Some(data_contract),
+            Err(e) => {
+                tracing::warn!("DiskCache: removing invalid data contract {:?}: {}", path, e);
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    fn store(&self, id: &Identifier, data_contract: &DataContract) {
+        match data_contract.serialize_to_bytes_with_platform_version(self.platform_version()) {
+            Ok(bytes) => write_file(&self.data_contract_path(id), &bytes),
+            Err(e) => tracing::warn!("DiskCache: cannot serialize data contract {}: {}", id.to_string(Encoding::Base58), e)
+        }
+    }
+}
+
+impl CacheStore<([u8; 32], u32), [u8; 48]> for DiskCache {
+    fn load(&self, (quorum_hash, quorum_type): &([u8; 32], u32)) -> Option<[u8; 48]> {
+        let bytes = fs::read(self.quorum_public_key_path(quorum_hash, *quorum_type)).ok()?;
+        bytes.try_into().ok()
+    }
+
+    fn store(&self, (quorum_hash, quorum_type): &([u8; 32], u32), public_key: &[u8; 48]) {
+        write_file(&self.quorum_public_key_path(quorum_hash, *quorum_type), public_key);
+    }
+}
+
+#[test]
+fn disk_cache_test() {
+    use std::num::NonZeroUsize;
+    use crate::provider::Cache;
+
+    let cache_dir = std::env::temp_dir().join("platform-mobile-disk-cache-test");
+    let _ = fs::remove_dir_all(&cache_dir);
+    // entries of another version are removed when the cache is opened
+    let stale_dir = cache_dir.join("cache-testnet").join("v0-p1");
+    fs::create_dir_all(&stale_dir).unwrap();
+
+    let disk_cache = DiskCache::open(cache_dir.to_str().unwrap(), &PlatformNetwork::Testnet, PlatformVersion::first()).unwrap();
+    assert!(!stale_dir.exists());
+
+    let key = ([1u8; 32], 4u32);
+    let cache: Cache<([u8; 32], u32), [u8; 48]> = Cache::with_store(NonZeroUsize::new(10).unwrap(), disk_cache.clone());
+    cache.put(key, [2u8; 48]);
+
+    // a new cache with an empty memory layer loads the key from disk
+    let cache: Cache<([u8; 32], u32), [u8; 48]> = Cache::with_store(NonZeroUsize::new(10).unwrap(), disk_cache.clone());
+    assert_eq!(cache.get(&key).map(|key| *key), Some([2u8; 48]));
+    assert!(cache.get(&([3u8; 32], 4u32)).is_none());
+
+    // the entries of the previous protocol version are removed when the network reports a new one
+    let first_dir = disk_cache.current.read().dir.clone();
+    let latest = PlatformVersion::latest().protocol_version;
+    disk_cache.set_protocol_version(latest);
+    assert!(!first_dir.exists());
+    let cache: Cache<([u8; 32], u32), [u8; 48]> = Cache::with_store(NonZeroUsize::new(10).unwrap(), disk_cache);
+    assert!(cache.get(&key).is_none());
+
+    // the reported version is used when the cache is opened again
+    let disk_cache = DiskCache::open(cache_dir.to_str().unwrap(), &PlatformNetwork::Testnet, PlatformVersion::first()).unwrap();
+    assert_eq!(disk_cache.platform_version().protocol_version, latest);
+
+    let _ = fs::remove_dir_all(&cache_dir);
+}
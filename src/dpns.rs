@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use dashcore::hashes::{Hash, sha256d};
+use dpp::document::{Document, DocumentV0Getters};
+use dpp::document::v0::DocumentV0;
+use dpp::identity::accessors::IdentityGettersV0;
+use dpp::identity::identity::Identity;
+use dpp::identity::identity_public_key::IdentityPublicKey;
+use dpp::util::entropy_generator::{DefaultEntropyGenerator, EntropyGenerator};
+use drive::query::{OrderClause, WhereClause, WhereOperator};
+use platform_value::{Identifier, Value};
+use crate::config::EntryPoint;
+use crate::error::PlatformError;
+use crate::put::{CallbackSigner, put_document_to_platform};
+use crate::fetch_document::{create_document_pager, document_pager_next_page, fetch_documents_with_query_and_sdk};
+use crate::request::SdkPtr;
+use crate::sdk::DashSdk;
+
+pub const DPNS_PARENT_DOMAIN: &str = "dash";
+
+/// Calculate the `saltedDomainHash` of a `preorder` document
+pub fn get_salted_domain_hash(
+    pre_order_salt_raw: &[u8],
+    full_name: &str
+) -> [u8; 32] {
+    let mut baos = Vec::with_capacity(pre_order_salt_raw.len() + full_name.len());
+    baos.write_all(pre_order_salt_raw).expect("Writing to buffer failed");
+    baos.write_all(full_name.as_bytes()).expect("Writing to buffer failed");
+
+    sha256d::Hash::hash(&baos.as_slice()).into()
+}
+
+pub const DPNS_LABEL_MIN_LENGTH: usize = 3;
+pub const DPNS_LABEL_MAX_LENGTH: usize = 63;
+
+/// Convert a label to its normalized form
+///
+/// The label is converted to lowercase and "o", "i" and "l" are replaced with "0" and "1"
+/// to mitigate homograph attacks, in the same way as the `normalizedLabel` of a `domain`.
+#[ferment_macro::export]
+pub fn normalize_dpns_label(label: String) -> String {
+    label.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'o' => '0',
+            'i' | 'l' => '1',
+            _ => c
+        })
+        .collect()
+}
+
+/// Check that a label can be registered with DPNS
+///
+/// The label must be 3 to 63 characters long, contain only letters, digits and hyphens
+/// and must not start or end with a hyphen.  Returns the normalized label.
+#[ferment_macro::export]
+pub fn validate_dpns_label(label: String) -> Result<String, PlatformError> {
+    let length = label.chars().count();
+    if length < DPNS_LABEL_MIN_LENGTH || length > DPNS_LABEL_MAX_LENGTH {
+        return Err(PlatformError::InvalidInput(
+            format!("label must be between {} and {} characters: {}", DPNS_LABEL_MIN_LENGTH, DPNS_LABEL_MAX_LENGTH, label)
+        ));
+    }
+    if let Some(c) = label.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '-') {
+        return Err(PlatformError::InvalidInput(format!("label contains an invalid character '{}': {}", c, label)));
+    }
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err(PlatformError::InvalidInput(format!("label cannot start or end with a hyphen: {}", label)));
+    }
+    Ok(normalize_dpns_label(label))
+}
+
+/// Check if a label is contested
+///
+/// Names that match the contested index of the `domain` document type, a normalized label of
+/// 3 to 19 characters made up of letters, "0", "1" and hyphens, are awarded by masternode vote.
+#[ferment_macro::export]
+pub fn is_contested_label(label: String) -> bool {
+    is_contested_normalized_label(&normalize_dpns_label(label))
+}
+
+pub(crate) fn is_contested_normalized_label(normalized_label: &str) -> bool {
+    let length = normalized_label.chars().count();
+    length >= 3 && length <= 19 && normalized_label.chars().all(|c| c.is_ascii_alphabetic() || c == '0' || c == '1' || c == '-')
+}
+
+pub(crate) fn new_document(owner_id: Identifier, properties: BTreeMap<String, Value>) -> Document {
+    Document::V0(
+        DocumentV0 {
+            id: Default::default(),
+            owner_id,
+            properties,
+            revision: Some(1),
+            created_at: None,
+            updated_at: None,
+            transferred_at: None,
+            created_at_block_height: None,
+            updated_at_block_height: None,
+            transferred_at_block_height: None,
+            created_at_core_block_height: None,
+            updated_at_core_block_height: None,
+            transferred_at_core_block_height: None,
+        }
+    )
+}
+
+/// Register `label`.dash for `identity`
+///
+/// Submits the `preorder` document, waits for it to be confirmed and then submits the
+/// `domain` document that reveals the name.  Contested names cannot be registered this way
+/// and return [PlatformError::UsernameContested].
+#[ferment_macro::export]
+pub fn register_username_sdk(
+    rust_sdk: *mut DashSdk,
+    label: String,
+    identity: Identity,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<Document, PlatformError> {
+    let normalized_label = validate_dpns_label(label.clone())?;
+    let full_name = format!("{}.{}", normalized_label, DPNS_PARENT_DOMAIN);
+    if is_contested_normalized_label(&normalized_label) {
+        return Err(PlatformError::UsernameContested(full_name));
+    }
+
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    rt.block_on(async {
+        let dpns_contract_id = Identifier::from(dpns_contract::ID_BYTES);
+        let owner_id = identity.id();
+        let entropy_generator = DefaultEntropyGenerator;
+        let preorder_salt = entropy_generator.generate()
+            .or_else(|e| Err(PlatformError::Generic(e.to_string())))?;
+
+        let mut preorder_props: BTreeMap<String, Value> = BTreeMap::new();
+        preorder_props.insert(
+            "saltedDomainHash".to_string(),
+            Value::Bytes32(get_salted_domain_hash(preorder_salt.as_slice(), &full_name))
+        );
+
+        tracing::info!("register_username_sdk: put preorder for {}", full_name);
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        put_document_to_platform(
+            SdkPtr(rust_sdk),
+            new_document(owner_id, preorder_props),
+            dpns_contract_id,
+            "preorder".to_string(),
+            identity_public_key.clone(),
+            0,
+            0,
+            signer
+        ).await?;
+
+        let records = vec![(Value::Text("identity".to_string()), Value::Identifier(owner_id.into()))];
+        let subdomain_rules = vec![(Value::Text("allowSubdomains".to_string()), Value::Bool(false))];
+        let mut domain_props: BTreeMap<String, Value> = BTreeMap::new();
+        domain_props.insert("records".to_string(), Value::Map(records));
+        domain_props.insert("label".to_string(), Value::Text(label.clone()));
+        domain_props.insert("preorderSalt".to_string(), Value::Bytes32(preorder_salt));
+        domain_props.insert("normalizedParentDomainName".to_string(), Value::Text(DPNS_PARENT_DOMAIN.to_string()));
+        domain_props.insert("parentDomainName".to_string(), Value::Text(DPNS_PARENT_DOMAIN.to_string()));
+        domain_props.insert("normalizedLabel".to_string(), Value::Text(normalized_label.clone()));
+        domain_props.insert("subdomainRules".to_string(), Value::Map(subdomain_rules));
+
+        tracing::info!("register_username_sdk: put domain for {}", full_name);
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        put_document_to_platform(
+            SdkPtr(rust_sdk),
+            new_document(owner_id, domain_props),
+            dpns_contract_id,
+            "domain".to_string(),
+            identity_public_key,
+            0,
+            0,
+            signer
+        ).await
+    })
+}
+
+/// A DPNS `domain` document
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub struct DomainInfo {
+    /// Id of the `domain` document
+    pub id: Identifier,
+    /// Identity that owns the `domain` document
+    pub owner_id: Identifier,
+    /// Identity that the name resolves to, from `records.identity`
+    pub identity_id: Option<Identifier>,
+    pub label: String,
+    pub normalized_label: String,
+    pub parent_domain_name: String,
+    pub normalized_parent_domain_name: String,
+    pub created_at: Option<u64>,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn DomainInfo_clone(value: DomainInfo) -> DomainInfo {
+    value.clone()
+}
+
+impl DomainInfo {
+    /// The full name, such as `alice.dash`
+    pub fn full_name(&self) -> String {
+        format!("{}.{}", self.label, self.parent_domain_name)
+    }
+}
+
+pub(crate) fn get_text_property(document: &Document, name: &str) -> String {
+    document.properties()
+        .get(name)
+        .and_then(|value| value.as_text())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn get_identity_record(document: &Document) -> Option<Identifier> {
+    match document.properties().get("records") {
+        Some(Value::Map(records)) => records.iter().find_map(|(key, value)| match key {
+            Value::Text(key) if key == "identity" => value.to_identifier().ok(),
+            _ => None
+        }),
+        _ => None
+    }
+}
+
+impl From<Document> for DomainInfo {
+    fn from(document: Document) -> Self {
+        DomainInfo {
+            id: document.id(),
+            owner_id: document.owner_id(),
+            identity_id: get_identity_record(&document),
+            label: get_text_property(&document, "label"),
+            normalized_label: get_text_property(&document, "normalizedLabel"),
+            parent_domain_name: get_text_property(&document, "parentDomainName"),
+            normalized_parent_domain_name: get_text_property(&document, "normalizedParentDomainName"),
+            created_at: document.created_at(),
+        }
+    }
+}
+
+/// Resolve a name such as `alice.dash` to the identity in its `records.identity`
+///
+/// A name without a parent domain is looked up under `dash`.
+#[ferment_macro::export]
+pub fn resolve_username(
+    rust_sdk: *mut DashSdk,
+    full_name: String
+) -> Result<Option<Identifier>, PlatformError> {
+    let (label, parent_domain_name) = match full_name.rsplit_once('.') {
+        Some((label, parent_domain_name)) => (label.to_string(), parent_domain_name.to_string()),
+        None => (full_name.clone(), DPNS_PARENT_DOMAIN.to_string())
+    };
+    let documents = fetch_documents_with_query_and_sdk(
+        rust_sdk,
+        Identifier::from(dpns_contract::ID_BYTES),
+        "domain".to_string(),
+        vec![
+            WhereClause { field: "normalizedParentDomainName".into(), value: Value::Text(normalize_dpns_label(parent_domain_name)), operator: WhereOperator::Equal },
+            WhereClause { field: "normalizedLabel".into(), value: Value::Text(normalize_dpns_label(label)), operator: WhereOperator::Equal }
+        ],
+        vec![],
+        1,
+        None
+    )?;
+
+    Ok(documents.first().and_then(get_identity_record))
+}
+
+/// List the `domain` documents that resolve to `identity_id`
+#[ferment_macro::export]
+pub fn usernames_for_identity(
+    rust_sdk: *mut DashSdk,
+    identity_id: Identifier
+) -> Result<Vec<DomainInfo>, PlatformError> {
+    // uses the identityId index, pages are ordered by $id within the same records.identity
+    let mut pager = create_document_pager(
+        rust_sdk,
+        Identifier::from(dpns_contract::ID_BYTES),
+        "domain".to_string(),
+        vec![
+            WhereClause { field: "records.identity".into(), value: Value::Identifier(identity_id.to_buffer()), operator: WhereOperator::Equal }
+        ],
+        vec![
+            OrderClause { field: "records.identity".into(), ascending: true }
+        ],
+        100
+    )?;
+    let mut domains = vec![];
+    while !pager.is_exhausted() {
+        domains.extend(document_pager_next_page(rust_sdk, &mut pager)?.into_iter().map(DomainInfo::from));
+    }
+    Ok(domains)
+}
+
+#[test]
+fn normalize_dpns_label_test() {
+    assert_eq!(normalize_dpns_label("my-unit-test-2".to_string()), "my-un1t-test-2");
+    assert_eq!(normalize_dpns_label("Bob".to_string()), "b0b");
+    assert_eq!(normalize_dpns_label("alice".to_string()), "a11ce");
+}
+
+#[test]
+fn validate_dpns_label_test() {
+    assert_eq!(validate_dpns_label("Alice-2".to_string()), Ok("a11ce-2".to_string()));
+    assert!(validate_dpns_label("ab".to_string()).is_err());
+    assert!(validate_dpns_label("a".repeat(64)).is_err());
+    assert!(validate_dpns_label("-alice".to_string()).is_err());
+    assert!(validate_dpns_label("alice-".to_string()).is_err());
+    assert!(validate_dpns_label("al_ice".to_string()).is_err());
+    assert!(validate_dpns_label("alice.dash".to_string()).is_err());
+}
+
+#[test]
+fn is_contested_label_test() {
+    assert!(is_contested_label("Bob".to_string()));
+    assert!(is_contested_label("alice-test".to_string()));
+    assert!(!is_contested_label("ab".to_string()));
+    assert!(!is_contested_label("bob2".to_string()));
+    assert!(!is_contested_label("a-very-long-username".to_string()));
+}
+
+#[test]
+fn resolve_username_test() {
+    use crate::sdk::create_dash_sdk_using_core_testnet;
+    let mut sdk = create_dash_sdk_using_core_testnet();
+    match resolve_username(&mut sdk, "test111.dash".to_string()) {
+        Ok(Some(identity_id)) => {
+            tracing::info!("identity: {}", identity_id);
+            match usernames_for_identity(&mut sdk, identity_id) {
+                Ok(domains) => {
+                    tracing::info!("domains: {:?}", domains);
+                    assert!(domains.iter().any(|domain| domain.normalized_label == "test111"));
+                }
+                Err(e) => panic!("{}", e)
+            }
+        }
+        Ok(None) => panic!("test111.dash is not registered"),
+        Err(e) => panic!("{}", e)
+    }
+}
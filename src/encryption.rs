@@ -0,0 +1,49 @@
+use aes::Aes256;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use crate::error::PlatformError;
+
+pub const AES_IV_SIZE: usize = 16;
+pub const AES_BLOCK_SIZE: usize = 16;
+
+type Aes256CbcEncryptor = cbc::Encryptor<Aes256>;
+type Aes256CbcDecryptor = cbc::Decryptor<Aes256>;
+
+/// Size of the result of [encrypt_aes_256_cbc] for `plain_text_size` bytes
+pub fn encrypted_size(plain_text_size: usize) -> usize {
+    AES_IV_SIZE + (plain_text_size / AES_BLOCK_SIZE + 1) * AES_BLOCK_SIZE
+}
+
+/// Encrypt with AES-CBC-256 and PKCS7 padding, the random IV is prepended to the cipher text
+pub fn encrypt_aes_256_cbc(key: &[u8; 32], plain_text: &[u8]) -> Vec<u8> {
+    let iv: [u8; AES_IV_SIZE] = rand::random();
+    let cipher_text = Aes256CbcEncryptor::new(key.into(), &iv.into())
+        .encrypt_padded_vec_mut::<Pkcs7>(plain_text);
+    let mut result = Vec::with_capacity(AES_IV_SIZE + cipher_text.len());
+    result.extend_from_slice(&iv);
+    result.extend_from_slice(&cipher_text);
+    result
+}
+
+/// Decrypt data that was encrypted with [encrypt_aes_256_cbc]
+pub fn decrypt_aes_256_cbc(key: &[u8; 32], encrypted: &[u8]) -> Result<Vec<u8>, PlatformError> {
+    if encrypted.len() < AES_IV_SIZE + AES_BLOCK_SIZE {
+        return Err(PlatformError::InvalidInput(format!("encrypted data is too short: {} bytes", encrypted.len())));
+    }
+    let (iv, cipher_text) = encrypted.split_at(AES_IV_SIZE);
+    Aes256CbcDecryptor::new(key.into(), iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(cipher_text)
+        .map_err(|_| PlatformError::InvalidInput("cannot decrypt: invalid key or padding".to_string()))
+}
+
+#[test]
+fn aes_256_cbc_test() {
+    let key = [7u8; 32];
+    for size in [0, 15, 16, 69] {
+        let plain_text = vec![1u8; size];
+        let encrypted = encrypt_aes_256_cbc(&key, &plain_text);
+        assert_eq!(encrypted.len(), encrypted_size(size));
+        assert_eq!(decrypt_aes_256_cbc(&key, &encrypted).unwrap(), plain_text);
+    }
+    assert!(decrypt_aes_256_cbc(&key, &[0u8; 16]).is_err());
+}
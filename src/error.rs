@@ -0,0 +1,197 @@
+use std::fmt;
+use dapi_grpc::platform::v0::StateTransitionBroadcastError;
+use dash_sdk::Error;
+use dpp::consensus::codes::ErrorWithCode;
+use dpp::data_contract::errors::DataContractError;
+use dpp::ProtocolError;
+use drive_proof_verifier::error::ContextProviderError;
+use rs_dapi_client::DapiClientError;
+use rs_dapi_client::transport::TransportError;
+use dapi_grpc::tonic::Code;
+
+/// Error returned by every exported function.
+///
+/// `dash_sdk::Error`, `ProtocolError` and `DapiClientError` are mapped onto these variants so that
+/// callers on Android and iOS can match on the kind of failure instead of parsing the message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub enum PlatformError {
+    /// The requested identity, document or other object does not exist on Platform
+    NotFound(String),
+    /// The data contract needed to build or verify the request is not available
+    DataContractNotFound(String),
+    /// The request did not complete within the configured timeout
+    Timeout(String),
+    /// Every evonode in the address list is banned
+    AllNodesBanned(String),
+    /// The proof returned by the evonode could not be verified
+    ProofVerificationFailed(String),
+    /// Platform rejected the state transition, the first value is the consensus error code
+    StateTransitionRejected(u32, String),
+    /// The signer callback could not sign the data
+    SignerFailed(String),
+    /// One of the arguments is not valid
+    InvalidInput(String),
+    /// The username is contested and can only be obtained through masternode voting
+    UsernameContested(String),
+    /// Any other error
+    Generic(String),
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn PlatformError_clone(error: PlatformError) -> PlatformError {
+    error.clone()
+}
+
+impl PlatformError {
+    pub fn message(&self) -> &str {
+        match self {
+            PlatformError::NotFound(message) => message,
+            PlatformError::DataContractNotFound(message) => message,
+            PlatformError::Timeout(message) => message,
+            PlatformError::AllNodesBanned(message) => message,
+            PlatformError::ProofVerificationFailed(message) => message,
+            PlatformError::StateTransitionRejected(_, message) => message,
+            PlatformError::SignerFailed(message) => message,
+            PlatformError::InvalidInput(message) => message,
+            PlatformError::UsernameContested(message) => message,
+            PlatformError::Generic(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for PlatformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlatformError::NotFound(message) => write!(f, "not found: {}", message),
+            PlatformError::DataContractNotFound(message) => write!(f, "data contract not found: {}", message),
+            PlatformError::Timeout(message) => write!(f, "timeout: {}", message),
+            PlatformError::AllNodesBanned(message) => write!(f, "all nodes banned: {}", message),
+            PlatformError::ProofVerificationFailed(message) => write!(f, "proof verification failed: {}", message),
+            PlatformError::StateTransitionRejected(code, message) => write!(f, "state transition rejected ({}): {}", code, message),
+            PlatformError::SignerFailed(message) => write!(f, "signer failed: {}", message),
+            PlatformError::InvalidInput(message) => write!(f, "invalid input: {}", message),
+            PlatformError::UsernameContested(message) => write!(f, "username is contested: {}", message),
+            PlatformError::Generic(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PlatformError {}
+
+// drive reports a missing contract inside of string based errors, so this is the only
+// place where the message needs to be inspected
+fn is_contract_not_found(message: &str) -> bool {
+    message.contains("contract not found")
+}
+
+impl From<&DapiClientError<TransportError>> for PlatformError {
+    fn from(error: &DapiClientError<TransportError>) -> Self {
+        let message = format!("{:?}", error);
+        match error {
+            DapiClientError::NoAvailableAddresses => PlatformError::AllNodesBanned(message),
+            DapiClientError::Transport(TransportError::Grpc(status), _) => match status.code() {
+                Code::DeadlineExceeded => PlatformError::Timeout(message),
+                Code::NotFound => PlatformError::NotFound(message),
+                _ => PlatformError::Generic(message)
+            },
+            _ => PlatformError::Generic(message)
+        }
+    }
+}
+
+impl From<DapiClientError<TransportError>> for PlatformError {
+    fn from(error: DapiClientError<TransportError>) -> Self {
+        PlatformError::from(&error)
+    }
+}
+
+impl From<Error> for PlatformError {
+    fn from(error: Error) -> Self {
+        let message = error.to_string();
+        match error {
+            Error::Protocol(protocol_error) => protocol_error.into(),
+            // dash_sdk::Error only keeps the debug output of the DapiClientError
+            Error::DapiClientError(message) => PlatformError::Generic(message),
+            Error::TimeoutReached(..) => PlatformError::Timeout(message),
+            Error::ContextProviderError(context_error) => context_error.into(),
+            Error::Proof(drive_proof_verifier::Error::ContextProviderError(context_error)) => context_error.into(),
+            Error::Proof(drive_proof_verifier::Error::NotFound) => PlatformError::NotFound(message),
+            Error::Proof(_) | Error::DriveProofError(..) | Error::InvalidProvedResponse(_) => {
+                if is_contract_not_found(&message) {
+                    PlatformError::DataContractNotFound(message)
+                } else {
+                    PlatformError::ProofVerificationFailed(message)
+                }
+            }
+            Error::Drive(_) if is_contract_not_found(&message) => PlatformError::DataContractNotFound(message),
+            Error::Config(_) => PlatformError::InvalidInput(message),
+            _ => {
+                if is_contract_not_found(&message) {
+                    PlatformError::DataContractNotFound(message)
+                } else {
+                    PlatformError::Generic(message)
+                }
+            }
+        }
+    }
+}
+
+impl From<ProtocolError> for PlatformError {
+    fn from(error: ProtocolError) -> Self {
+        let message = error.to_string();
+        match error {
+            ProtocolError::ConsensusError(consensus_error) => {
+                PlatformError::StateTransitionRejected(consensus_error.code(), message)
+            }
+            ProtocolError::InvalidSigningKeyTypeError(_) => PlatformError::SignerFailed(message),
+            ProtocolError::IdentifierError(_) | ProtocolError::DataContractError(_) => PlatformError::InvalidInput(message),
+            _ => {
+                if is_contract_not_found(&message) {
+                    PlatformError::DataContractNotFound(message)
+                } else {
+                    PlatformError::Generic(message)
+                }
+            }
+        }
+    }
+}
+
+impl From<DataContractError> for PlatformError {
+    fn from(error: DataContractError) -> Self {
+        PlatformError::InvalidInput(error.to_string())
+    }
+}
+
+impl From<ContextProviderError> for PlatformError {
+    fn from(error: ContextProviderError) -> Self {
+        let message = error.to_string();
+        match error {
+            ContextProviderError::DataContractFailure(_) => PlatformError::DataContractNotFound(message),
+            _ => PlatformError::ProofVerificationFailed(message)
+        }
+    }
+}
+
+impl From<&StateTransitionBroadcastError> for PlatformError {
+    fn from(error: &StateTransitionBroadcastError) -> Self {
+        PlatformError::StateTransitionRejected(error.code, error.message.clone())
+    }
+}
+
+#[test]
+fn dapi_client_error_test() {
+    use std::str::FromStr;
+    use rs_dapi_client::Address;
+    use dapi_grpc::tonic::Status;
+
+    let address = Address::from_str("https://35.165.50.126:1443").unwrap();
+    let transport = |status: Status| DapiClientError::Transport(TransportError::Grpc(status), address.clone());
+    assert!(matches!(PlatformError::from(transport(Status::deadline_exceeded("timeout"))), PlatformError::Timeout(_)));
+    assert!(matches!(PlatformError::from(transport(Status::not_found("missing"))), PlatformError::NotFound(_)));
+    assert!(matches!(PlatformError::from(transport(Status::unavailable("down"))), PlatformError::Generic(_)));
+    assert!(matches!(PlatformError::from(DapiClientError::<TransportError>::NoAvailableAddresses), PlatformError::AllNodesBanned(_)));
+    // the message of an untyped DapiClientError is not inspected
+    assert!(matches!(PlatformError::from(Error::DapiClientError("DeadlineExceeded".to_string())), PlatformError::Generic(_)));
+}
@@ -0,0 +1,381 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use dash_sdk::Sdk;
+use dpp::data_contract::DataContract;
+use parking_lot::{Mutex, RwLock};
+use platform_value::Identifier;
+use tokio::runtime::Runtime;
+use tokio::task::AbortHandle;
+use crate::config::{Config, EntryPoint, PlatformNetwork, testnet_address_list};
+use crate::disk_cache::{DiskCache, unique_temp_path};
+use crate::error::PlatformError;
+use crate::health::{check_node, NodeHealthTracker};
+use crate::probe::NodeProbeQuery;
+use crate::provider::Cache;
+use crate::sdk::{DashSdk, DashSdkOptions};
+
+/// Writes the current evonodes of the masternode list to `result` as UTF-8 text, one `host` or
+/// `host:port` per line, and returns the number of bytes written.  Returns 0 if the masternode
+/// list is not available yet.
+pub type EvonodeListCallback = extern "C" fn(context: usize, result: * mut u8, result_len: u32) -> u32;
+
+const EVONODE_LIST_BUFFER_SIZE: usize = 64 * 1024;
+/// Number of evonodes that are checked in each health check
+const NODES_PER_HEALTH_CHECK: usize = 10;
+
+/// The evonodes that the SDK connects to
+///
+/// The list starts with the address list of the options, the last known good set that was
+/// saved in `cache_dir` or the seed list of the network, in that order.  It is replaced when
+/// the host provides a newer masternode list, which is saved once one of its evonodes responded.
+pub struct EvonodeList {
+    path: Option<PathBuf>,
+    evonodes: Mutex<Vec<String>>,
+    /// The last known good set in `cache_dir`
+    saved: Mutex<Vec<String>>,
+    /// The address list of the options, it is used for this session only and never saved
+    explicit: Vec<String>,
+    /// The evonodes that the current [Sdk] was built with
+    active: Mutex<Vec<String>>,
+    tasks: Mutex<Vec<AbortHandle>>,
+}
+
+impl EvonodeList {
+    pub fn new(options: &DashSdkOptions) -> Self {
+        let path = evonode_list_path(&options.cache_dir, &options.network);
+        let saved = path.as_ref().map(load_evonode_list).unwrap_or_default();
+        let evonodes = if !options.address_list.is_empty() {
+            options.address_list.clone()
+        } else if !saved.is_empty() {
+            tracing::info!("EvonodeList: using {} saved evonodes", saved.len());
+            saved.clone()
+        } else {
+            options.network.default_address_list().iter().map(|&s| s.to_string()).collect()
+        };
+        EvonodeList {
+            path,
+            evonodes: Mutex::new(evonodes),
+            saved: Mutex::new(saved),
+            explicit: options.address_list.clone(),
+            active: Mutex::new(vec![]),
+            tasks: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn get(&self) -> Vec<String> {
+        self.evonodes.lock().clone()
+    }
+
+    /// Replace the list, it is saved by [EvonodeList::save] once one of the evonodes responded
+    pub fn set(&self, evonodes: Vec<String>) {
+        *self.evonodes.lock() = evonodes;
+    }
+
+    /// Save `evonodes` as the last known good set after one of them responded to a probe.
+    /// Nothing is saved if the list has been replaced since it was probed or is already saved.
+    pub fn save(&self, evonodes: &Vec<String>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if *self.evonodes.lock() != *evonodes || self.explicit == *evonodes {
+            return;
+        }
+        let mut saved = self.saved.lock();
+        if *saved == *evonodes {
+            return;
+        }
+        match save_evonode_list(path, evonodes) {
+            Ok(()) => *saved = evonodes.clone(),
+            Err(e) => tracing::warn!("EvonodeList: failed to save {:?}: {}", path, e)
+        }
+    }
+
+    pub fn get_active(&self) -> Vec<String> {
+        self.active.lock().clone()
+    }
+
+    pub fn set_active(&self, evonodes: Vec<String>) {
+        *self.active.lock() = evonodes;
+    }
+
+    /// Stop refreshing the list and checking the health of the evonodes
+    pub fn stop_tasks(&self) {
+        for task in self.tasks.lock().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+fn evonode_list_path(cache_dir: &str, network: &PlatformNetwork) -> Option<PathBuf> {
+    if cache_dir.is_empty() {
+        return None;
+    }
+    Some(PathBuf::from(cache_dir).join(format!("evonodes-{}.txt", network.name())))
+}
+
+/// Parse a list of evonodes separated by new lines or commas, duplicates are removed
+pub fn parse_evonode_list(text: &str) -> Vec<String> {
+    let mut evonodes: Vec<String> = vec![];
+    for evonode in text.split(|c| c == '\n' || c == ',').map(str::trim) {
+        if !evonode.is_empty() && !evonodes.iter().any(|e| e == evonode) {
+            evonodes.push(evonode.to_string());
+        }
+    }
+    evonodes
+}
+
+fn load_evonode_list(path: &PathBuf) -> Vec<String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_evonode_list(&text),
+        Err(_) => vec![]
+    }
+}
+
+fn save_evonode_list(path: &PathBuf, evonodes: &Vec<String>) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // write to a temporary file first so that a crash does not leave a partial list,
+    // several DashSdk instances can save the list at the same time
+    let temp_path = unique_temp_path(path);
+    let result = fs::write(&temp_path, evonodes.join("\n")).and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn evonodes_from_callback(context: usize, callback: u64) -> Vec<String> {
+    let callback: EvonodeListCallback = unsafe { std::mem::transmute(callback as usize) };
+    let mut result = vec![0u8; EVONODE_LIST_BUFFER_SIZE];
+    let length = callback(context, result.as_mut_ptr(), result.len() as u32) as usize;
+    if length == 0 || length > result.len() {
+        return vec![];
+    }
+    match std::str::from_utf8(&result[..length]) {
+        Ok(text) => parse_evonode_list(text),
+        Err(e) => {
+            tracing::warn!("evonodes_from_callback: invalid list: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// Everything that is needed to rebuild the [Sdk] with a new list of evonodes
+#[derive(Clone)]
+pub struct EvonodeRefresher {
+    pub config: Arc<Config>,
+    pub options: DashSdkOptions,
+    pub data_contract_cache: Arc<Cache<Identifier, DataContract>>,
+    pub quorum_public_keys_cache: Arc<Cache<([u8; 32], u32), [u8; 48]>>,
+    /// The disk cache of the data contracts and quorum public keys, opened once per [DashSdk]
+    pub disk_cache: Option<Arc<DiskCache>>,
+    pub sdk: Arc<RwLock<Arc<Sdk>>>,
+    pub evonode_list: Arc<EvonodeList>,
+    pub health: Arc<NodeHealthTracker>,
+}
+
+impl EvonodeRefresher {
+    /// Get the current evonodes from the host and rebuild the [Sdk] when they have changed.
+    /// Returns the number of evonodes.
+    pub async fn refresh(&self) -> Result<usize, PlatformError> {
+        if self.options.evonode_list_callback == 0 {
+            return Err(PlatformError::InvalidInput("evonode_list_callback is not set".to_string()));
+        }
+        let evonodes = evonodes_from_callback(
+            self.options.evonode_list_context,
+            self.options.evonode_list_callback
+        );
+        if evonodes.is_empty() {
+            return Err(PlatformError::NotFound("the masternode list is not available".to_string()));
+        }
+        let count = evonodes.len();
+        if evonodes != self.evonode_list.get() {
+            tracing::info!("EvonodeRefresher: updating the sdk with {} evonodes", count);
+            self.update_sdk(evonodes).await;
+        }
+        Ok(count)
+    }
+
+    /// Rebuild the [Sdk] with `evonodes`, they are saved as the last known good set
+    /// once the best of them responds to a probe
+    pub async fn update_sdk(&self, evonodes: Vec<String>) {
+        self.evonode_list.set(evonodes.clone());
+        self.rebuild_sdk().await;
+
+        let refresher = self.clone();
+        tokio::spawn(async move {
+            if let Some(address) = refresher.evonode_list.get_active().first() {
+                if refresher.probe(address).await {
+                    refresher.evonode_list.save(&evonodes);
+                }
+            }
+        });
+    }
+
+    /// Check the health of the evonode at `address`, returns true if it responded
+    async fn probe(&self, address: &str) -> bool {
+        match check_node(self, address, NodeProbeQuery::DataContract).await {
+            Ok((latency, metadata)) => {
+                self.health.record_success(address, latency);
+                self.set_protocol_version(metadata.protocol_version);
+                true
+            }
+            Err(e) => {
+                self.health.record_failure(address, &e);
+                false
+            }
+        }
+    }
+
+    /// Build a new [Sdk] with the healthiest evonodes of the list
+    pub async fn rebuild_sdk(&self) {
+        let preferred = self.health.preferred_nodes(
+            &self.evonode_list.get(),
+            &self.evonode_list.get_active(),
+            self.options.preferred_node_count as usize
+        );
+        let options = DashSdkOptions {
+            address_list: preferred.clone(),
+            ..self.options.clone()
+        };
+        let sdk = self.config.setup_api_with_options(
+            &options,
+            self.data_contract_cache.clone(),
+            self.quorum_public_keys_cache.clone()
+        ).await;
+        *self.sdk.write() = sdk;
+        self.evonode_list.set_active(preferred);
+    }
+
+    /// Key the disk cache on `protocol_version`, as reported by an evonode
+    pub fn set_protocol_version(&self, protocol_version: u32) {
+        if let Some(disk_cache) = &self.disk_cache {
+            disk_cache.set_protocol_version(protocol_version);
+        }
+    }
+
+    /// Check the evonodes that were not checked for the longest time and rebuild the [Sdk]
+    /// when the preferred evonodes have changed
+    pub async fn check_health(&self) {
+        let evonodes = self.evonode_list.get();
+        let mut handles = vec![];
+        for address in self.health.nodes_to_check(&evonodes, NODES_PER_HEALTH_CHECK) {
+            let refresher = self.clone();
+            handles.push(tokio::spawn(async move {
+                refresher.probe(&address).await
+            }));
+        }
+        let mut responded = false;
+        for handle in handles {
+            responded |= handle.await.unwrap_or(false);
+        }
+        if responded {
+            self.evonode_list.save(&evonodes);
+        }
+
+        let mut preferred = self.health.preferred_nodes(
+            &evonodes,
+            &self.evonode_list.get_active(),
+            self.options.preferred_node_count as usize
+        );
+        let mut active = self.evonode_list.get_active();
+        preferred.sort();
+        active.sort();
+        if preferred != active {
+            tracing::info!("EvonodeRefresher: updating the sdk with {} preferred evonodes", preferred.len());
+            self.rebuild_sdk().await;
+        }
+    }
+
+    /// Refresh the evonodes every `evonode_refresh_interval_s` seconds and check
+    /// their health every `health_check_interval_s` seconds
+    pub fn start(&self, runtime: &Runtime) {
+        let mut tasks = self.evonode_list.tasks.lock();
+        if self.options.evonode_list_callback != 0 && self.options.evonode_refresh_interval_s != 0 {
+            let refresher = self.clone();
+            let period = Duration::from_secs(self.options.evonode_refresh_interval_s);
+            let handle = runtime.spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = refresher.refresh().await {
+                        tracing::warn!("EvonodeRefresher: refresh failed: {}", e);
+                    }
+                }
+            });
+            tasks.push(handle.abort_handle());
+        }
+        if self.options.health_check_interval_s != 0 {
+            let refresher = self.clone();
+            let period = Duration::from_secs(self.options.health_check_interval_s);
+            let handle = runtime.spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                // the first tick completes immediately, the evonodes are checked after the first period
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    refresher.check_health().await;
+                }
+            });
+            tasks.push(handle.abort_handle());
+        }
+    }
+}
+
+/// Get the evonodes that the SDK is using
+#[ferment_macro::export]
+pub fn get_evonode_list(rust_sdk: *mut DashSdk) -> Vec<String> {
+    unsafe { (*rust_sdk).get_evonode_refresher().evonode_list.get() }
+}
+
+/// Get the current evonodes from the evonode list callback and update the SDK now.
+/// Returns the number of evonodes.
+#[ferment_macro::export]
+pub fn refresh_evonode_list(rust_sdk: *mut DashSdk) -> Result<u32, PlatformError> {
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    let refresher = unsafe { (*rust_sdk).get_evonode_refresher() };
+    rt.block_on(async move {
+        refresher.refresh().await.map(|count| count as u32)
+    })
+}
+
+#[test]
+fn parse_evonode_list_test() {
+    let evonodes = parse_evonode_list("34.214.48.68\n35.165.50.126:1443, 34.214.48.68\n\n");
+    assert_eq!(evonodes, vec!["34.214.48.68".to_string(), "35.165.50.126:1443".to_string()]);
+}
+
+#[test]
+fn evonode_list_test() {
+    let cache_dir = std::env::temp_dir().join(format!("evonode-list-test-{}", std::process::id()));
+    let options = DashSdkOptions {
+        cache_dir: cache_dir.to_string_lossy().to_string(),
+        ..crate::sdk::default_dash_sdk_options(PlatformNetwork::Testnet)
+    };
+    let evonodes = vec!["34.214.48.68".to_string(), "35.165.50.126".to_string()];
+
+    // nothing is saved until a probe succeeded
+    let list = EvonodeList::new(&options);
+    list.set(evonodes.clone());
+    assert_eq!(EvonodeList::new(&options).get(), testnet_address_list());
+    // a list that was replaced after the probe is not saved
+    list.save(&vec!["52.42.202.128".to_string()]);
+    assert_eq!(EvonodeList::new(&options).get(), testnet_address_list());
+    list.save(&evonodes);
+    assert_eq!(EvonodeList::new(&options).get(), evonodes);
+
+    // an explicit address list wins over the saved list
+    let single = DashSdkOptions {
+        address_list: vec!["52.42.202.128".to_string()],
+        ..options.clone()
+    };
+    let list = EvonodeList::new(&single);
+    assert_eq!(list.get(), vec!["52.42.202.128".to_string()]);
+    list.save(&list.get());
+    assert_eq!(EvonodeList::new(&options).get(), evonodes);
+
+    let _ = fs::remove_dir_all(cache_dir);
+}
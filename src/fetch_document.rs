@@ -1,5 +1,6 @@
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use dash_sdk::platform::{DocumentQuery, Fetch, FetchMany};
 use dapi_grpc::platform::v0::get_documents_request::get_documents_request_v0::Start;
 use dash_sdk::platform::proto::GetDataContractRequest;
@@ -9,6 +10,7 @@ use dpp::data_contract::DataContract;
 use dpp::document::{Document, DocumentV0Getters};
 use drive::query::{ordering::OrderClause, conditions::WhereClause, conditions::WhereOperator};
 use platform_value::{types::identifier::Identifier, IdentifierBytes32, Value};
+use platform_value::string_encoding::Encoding;
 use tokio::runtime::{Builder, Runtime};
 use crate::config::{Config, EntryPoint};
 use crate::logs::setup_logs;
@@ -22,6 +24,10 @@ use dpp::version::LATEST_PLATFORM_VERSION;
 use dpp::data_contract::accessors::v0::DataContractV0Getters;
 use dpp::document::serialization_traits::DocumentPlatformConversionMethodsV0;
 use crate::provider::Cache;
+use crate::error::PlatformError;
+use crate::request::{RequestResult, SdkPtr, spawn_request};
+use crate::health::RequestRecorder;
+use ferment_interfaces::unbox_any;
 
 #[ferment_macro::export]
 pub fn document_to_string(document: Document)-> String {
@@ -48,17 +54,20 @@ fn fetch_documents_with_retry(
     data_contract_cache: Arc<Cache<Identifier, DataContract>>,
     query: DocumentQuery,
     request_settings: RequestSettings,
+    recorder: RequestRecorder,
     retries_left: usize,
-) -> BoxFuture<'static, Result<Documents, Error>> {
+) -> BoxFuture<'static, Result<Documents, PlatformError>> {
     // Clone the Arc<Sdk> here to ensure it's owned by the future
     Box::pin(async move {
         match Document::fetch_many_with_settings(&sdk, query.clone(), request_settings).await {
             Ok(documents) => Ok(documents),
             Err(error) => {
+                recorder.record_error(&error);
+                let error = PlatformError::from(error);
                 if retries_left > 1 {
-                    if error.to_string().contains("contract not found error: contract not found when querying from value with contract info") {
+                    if let PlatformError::DataContractNotFound(_) = error {
                         if (data_contract_cache.get(&query.data_contract.id()) != None) {
-                            return fetch_documents_with_retry(sdk, data_contract_cache, query, request_settings, retries_left - 1).await;
+                            return fetch_documents_with_retry(sdk, data_contract_cache, query, request_settings, recorder, retries_left - 1).await;
                         }
                     }
                 }
@@ -77,72 +86,310 @@ pub fn fetch_documents_with_query_and_sdk(
                                   order_clauses: Vec<OrderClause>,
                                   limit: u32,
                                   start: Option<StartPoint>
-) -> Result<Vec<Document>, String> {
+) -> Result<Vec<Document>, PlatformError> {
     let rt = unsafe { (*rust_sdk).get_runtime() };
 
     // Execute the async block using the Tokio runtime
-    rt.block_on(async {
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
+    rt.block_on(fetch_documents_with_query(
+        SdkPtr(rust_sdk),
+        data_contract_id,
+        document_type,
+        where_clauses,
+        order_clauses,
+        limit,
+        start
+    ))
+}
 
-        tracing::warn!("using existing data contract id and fetching...");
+/// Non-blocking version of [fetch_documents_with_query_and_sdk]
+///
+/// Returns the request id, the result is [RequestResult::Documents].
+#[ferment_macro::export]
+pub fn fetch_documents_with_query_and_sdk_async(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    document_type: String,
+    where_clauses: Vec<WhereClause>,
+    order_clauses: Vec<OrderClause>,
+    limit: u32,
+    start: Option<StartPoint>,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        fetch_documents_with_query(
+            rust_sdk,
+            data_contract_id,
+            document_type,
+            where_clauses,
+            order_clauses,
+            limit,
+            start
+        ).await.map(RequestResult::Documents)
+    })
+}
 
-        let contract = match unsafe { (*rust_sdk).get_data_contract(&data_contract_id) } {
-            Some(data_contract) => data_contract.clone(),
-            None => {
-                let request_settings = unsafe { (*rust_sdk).get_request_settings() };
-                match (DataContract::fetch_with_settings(&sdk, data_contract_id.clone(), request_settings)
-                         .await) {
-                    Ok(Some(data_contract)) => {
-                        unsafe { (*rust_sdk).add_data_contract(&data_contract); };
-                        Arc::new(data_contract)
-                    },
-                    Ok(None) => return Err("data contract not found".to_string()),
-                    Err(e) => return Err(e.to_string())
-                }
+pub async fn fetch_documents_with_query(
+    rust_sdk: SdkPtr,
+    data_contract_id: Identifier,
+    document_type: String,
+    where_clauses: Vec<WhereClause>,
+    order_clauses: Vec<OrderClause>,
+    limit: u32,
+    start: Option<StartPoint>
+) -> Result<Vec<Document>, PlatformError> {
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+
+    tracing::warn!("using existing data contract id and fetching...");
+
+    let contract = match unsafe { (*rust_sdk.0).get_data_contract(&data_contract_id) } {
+        Some(data_contract) => data_contract.clone(),
+        None => {
+            let request_settings = unsafe { (*rust_sdk.0).get_request_settings() };
+            match (DataContract::fetch_with_settings(&sdk, data_contract_id.clone(), request_settings)
+                     .await) {
+                Ok(Some(data_contract)) => {
+                    unsafe { (*rust_sdk.0).add_data_contract(&data_contract); };
+                    Arc::new(data_contract)
+                },
+                Ok(None) => return Err(PlatformError::DataContractNotFound(data_contract_id.to_string(Encoding::Base58))),
+                Err(e) => return Err(e.into())
             }
-        };
+        }
+    };
 
-        tracing::warn!("contract_fetch_result: {:?}", contract);
+    tracing::warn!("contract_fetch_result: {:?}", contract);
 
-        tracing::warn!("fetching many...");
-        // Fetch multiple documents so that we get document ID
-        let mut all_docs_query =
-            DocumentQuery::new(Arc::clone(&contract), &document_type)
-                .expect("create SdkDocumentQuery");
-        for wc in where_clauses {
-            all_docs_query = all_docs_query.with_where(wc);
+    tracing::warn!("fetching many...");
+    // Fetch multiple documents so that we get document ID
+    let mut all_docs_query =
+        DocumentQuery::new(Arc::clone(&contract), &document_type)
+            .expect("create SdkDocumentQuery");
+    for wc in where_clauses {
+        all_docs_query = all_docs_query.with_where(wc);
+    }
+    for oc in order_clauses {
+        all_docs_query = all_docs_query.with_order_by(oc);
+    }
+    all_docs_query.limit = limit;
+    all_docs_query.start = match start {
+        Some(s) => Some(s.into()),
+        None => None
+    };
+    let settings = unsafe { (*rust_sdk.0).get_request_settings() };
+    tracing::warn!("fetching many... query created");
+    let extra_retries = match settings.retries {
+        Some(retries) => retries,
+        None => 5 as usize
+    };
+    let data_contract_cache = unsafe { (*rust_sdk.0).data_contract_cache.clone() };
+    let recorder = unsafe { (*rust_sdk.0).get_request_recorder() };
+    match fetch_documents_with_retry(sdk.clone(), data_contract_cache, all_docs_query, settings, recorder, extra_retries).await {
+        Ok(docs) => {
+            tracing::warn!("convert to Vec");
+            let into_vec = |map: BTreeMap<Identifier, Option<Document>>| {
+                map.into_iter()
+                    .filter_map(|(_key, value)| value)
+                    .collect::<Vec<Document>>()
+            };
+
+            Ok(into_vec(docs))
         }
-        for oc in order_clauses {
-            all_docs_query = all_docs_query.with_order_by(oc);
+        Err(e) => Err(e.into())
+    }
+}
+
+/// Pages through the results of a document query.
+///
+/// The pager keeps the query and the id of the last document that was returned, so that
+/// each call to [document_pager_next_page] continues with [Start::StartAfter] where the
+/// previous page ended.
+#[ferment_macro::opaque]
+pub struct DocumentPager {
+    query: DocumentQuery,
+    order_clauses: Vec<OrderClause>,
+    // shared with the requests of [document_pager_next_page_async]
+    cursor: Arc<Mutex<PagerCursor>>,
+}
+
+/// Platform returns at most 100 documents per request
+const MAX_PAGE_SIZE: u32 = 100;
+
+#[derive(Default)]
+struct PagerCursor {
+    last_document_id: Option<Identifier>,
+    exhausted: bool,
+}
+
+impl PagerCursor {
+    // move the cursor past `page`, a page that is not full means that there are no more results
+    fn advance(&mut self, page: &[Document], limit: usize) {
+        if page.len() < limit {
+            self.exhausted = true;
         }
-        all_docs_query.limit = limit;
-        all_docs_query.start = match start {
-            Some(s) => Some(s.into()),
-            None => None
-        };
-        let settings = unsafe { (*rust_sdk).get_request_settings() };
-        tracing::warn!("fetching many... query created");
-        let extra_retries = match settings.retries {
-            Some(retries) => retries,
-            None => 5 as usize
-        };
-        let data_contract_cache = unsafe { (*rust_sdk).data_contract_cache.clone() };
-        match fetch_documents_with_retry(sdk.clone(), data_contract_cache, all_docs_query, settings, extra_retries).await {
-            Ok(docs) => {
-                tracing::warn!("convert to Vec");
-                let into_vec = |map: BTreeMap<Identifier, Option<Document>>| {
-                    map.into_iter()
-                        .filter_map(|(_key, value)| value)
-                        .collect::<Vec<Document>>()
-                };
+        match page.last() {
+            Some(document) => self.last_document_id = Some(document.id()),
+            None => self.exhausted = true
+        }
+    }
+}
 
-                Ok(into_vec(docs))
+// a page size of 0 uses the largest page, larger page sizes are limited to it
+fn page_limit(page_size: u32) -> u32 {
+    if page_size == 0 { MAX_PAGE_SIZE } else { page_size.clamp(1, MAX_PAGE_SIZE) }
+}
+
+impl DocumentPager {
+    pub fn is_exhausted(&self) -> bool {
+        self.cursor.lock().unwrap().exhausted
+    }
+}
+
+// value used to order documents of a page in the same way as the query
+fn document_order_value(document: &Document, field: &str) -> Value {
+    match field {
+        "$id" => Value::Identifier(document.id().to_buffer()),
+        "$ownerId" => Value::Identifier(document.owner_id().to_buffer()),
+        "$createdAt" => document.created_at().map(Value::U64).unwrap_or(Value::Null),
+        "$updatedAt" => document.updated_at().map(Value::U64).unwrap_or(Value::Null),
+        _ => document.properties().get(field).cloned().unwrap_or(Value::Null)
+    }
+}
+
+// Documents are returned in a map keyed by id, so restore the order of the query
+// before the last document is used as the cursor for the next page
+pub(crate) fn sort_documents(documents: &mut Vec<Document>, order_clauses: &Vec<OrderClause>) {
+    documents.sort_by(|a, b| {
+        for order_clause in order_clauses {
+            let ordering = document_order_value(a, &order_clause.field)
+                .partial_cmp(&document_order_value(b, &order_clause.field))
+                .unwrap_or(Ordering::Equal);
+            let ordering = if order_clause.ascending { ordering } else { ordering.reverse() };
+            if ordering != Ordering::Equal {
+                return ordering;
             }
-            Err(e) => Err(e.to_string())
         }
+        a.id().cmp(&b.id())
+    });
+}
+
+#[ferment_macro::export]
+pub fn create_document_pager(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    document_type: String,
+    where_clauses: Vec<WhereClause>,
+    order_clauses: Vec<OrderClause>,
+    page_size: u32
+) -> Result<DocumentPager, PlatformError> {
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+
+    rt.block_on(async {
+        let contract = unsafe { (*rust_sdk).get_or_fetch_data_contract(&data_contract_id) }.await?;
+
+        let mut query = DocumentQuery::new(Arc::clone(&contract), &document_type)?;
+        for wc in where_clauses {
+            query = query.with_where(wc);
+        }
+        for oc in order_clauses.iter() {
+            query = query.with_order_by(oc.clone());
+        }
+        query.limit = page_limit(page_size);
+
+        Ok(DocumentPager {
+            query,
+            order_clauses,
+            cursor: Arc::new(Mutex::new(PagerCursor::default())),
+        })
     })
 }
 
+/// Fetch the next page of documents.
+///
+/// Returns an empty list once the result set is exhausted.
+#[ferment_macro::export]
+pub fn document_pager_next_page(
+    rust_sdk: *mut DashSdk,
+    pager: *mut DocumentPager
+) -> Result<Vec<Document>, PlatformError> {
+    let pager = unsafe { &*pager };
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+
+    rt.block_on(fetch_next_page(
+        SdkPtr(rust_sdk),
+        pager.query.clone(),
+        pager.order_clauses.clone(),
+        pager.cursor.clone()
+    ))
+}
+
+/// Non-blocking version of [document_pager_next_page]
+///
+/// Returns the request id, the result is [RequestResult::Documents].  The next page
+/// must not be requested before this request has completed.
+#[ferment_macro::export]
+pub fn document_pager_next_page_async(
+    rust_sdk: *mut DashSdk,
+    pager: *mut DocumentPager,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let pager = unsafe { &*pager };
+    let query = pager.query.clone();
+    let order_clauses = pager.order_clauses.clone();
+    let cursor = pager.cursor.clone();
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        fetch_next_page(rust_sdk, query, order_clauses, cursor)
+            .await
+            .map(RequestResult::Documents)
+    })
+}
+
+async fn fetch_next_page(
+    rust_sdk: SdkPtr,
+    query: DocumentQuery,
+    order_clauses: Vec<OrderClause>,
+    cursor: Arc<Mutex<PagerCursor>>
+) -> Result<Vec<Document>, PlatformError> {
+    let mut query = query;
+    {
+        let cursor = cursor.lock().unwrap();
+        if cursor.exhausted {
+            return Ok(vec![]);
+        }
+        query.start = cursor.last_document_id.map(|id| Start::StartAfter(id.to_vec()));
+    }
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    let settings = unsafe { (*rust_sdk.0).get_request_settings() };
+    let extra_retries = settings.retries.unwrap_or(5);
+    let data_contract_cache = unsafe { (*rust_sdk.0).data_contract_cache.clone() };
+    let limit = query.limit as usize;
+
+    let recorder = unsafe { (*rust_sdk.0).get_request_recorder() };
+    let documents = fetch_documents_with_retry(sdk.clone(), data_contract_cache, query, settings, recorder, extra_retries).await?;
+    let mut page: Vec<Document> = documents.into_iter()
+        .filter_map(|(_key, value)| value)
+        .collect();
+    sort_documents(&mut page, &order_clauses);
+
+    let mut cursor = cursor.lock().unwrap();
+    cursor.advance(&page, limit);
+    tracing::info!("document_pager_next_page: {} documents, exhausted: {}", page.len(), cursor.exhausted);
+    Ok(page)
+}
+
+#[ferment_macro::export]
+pub fn document_pager_is_exhausted(pager: *mut DocumentPager) -> bool {
+    unsafe { (*pager).is_exhausted() }
+}
+
+#[ferment_macro::export]
+pub fn destroy_document_pager(pager: *mut DocumentPager) {
+    unsafe { unbox_any(pager) };
+}
+
 #[ferment_macro::export]
 pub unsafe fn fetch_documents_with_query_and_sdk2(
     rust_sdk: *mut DashSdk,
@@ -152,7 +399,7 @@ pub unsafe fn fetch_documents_with_query_and_sdk2(
     order_clauses: Vec<OrderClause>,
     limit: u32,
     start: Option<StartPoint>
-) -> Result<Vec<Document>, String> {
+) -> Result<Vec<Document>, PlatformError> {
     let rt = (*rust_sdk).get_runtime();
 
     // Execute the async block using the Tokio runtime
@@ -171,8 +418,8 @@ pub unsafe fn fetch_documents_with_query_and_sdk2(
                         unsafe { (*rust_sdk).add_data_contract(&data_contract); };
                         Arc::new(data_contract)
                     },
-                    Ok(None) => return Err("data contract not found".to_string()),
-                    Err(e) => return Err(e.to_string())
+                    Ok(None) => return Err(PlatformError::DataContractNotFound(data_contract_id.to_string(Encoding::Base58))),
+                    Err(e) => return Err(e.into())
                 }
             }
         };
@@ -211,7 +458,7 @@ pub unsafe fn fetch_documents_with_query_and_sdk2(
 
                 Ok(into_vec(docs))
             }
-            Err(e) => Err(e.to_string())
+            Err(e) => Err(e.into())
         }
     })
 }
@@ -240,34 +487,46 @@ pub unsafe fn deserialize_document_sdk(
     bytes: Vec<u8>,
     data_contract_id: Identifier,
     document_type: String
-) -> Result<Document, String> {
+) -> Result<Document, PlatformError> {
 
     let rt = (*rust_sdk).get_runtime();
 
     // Execute the async block using the Tokio runtime
-    rt.block_on(async {
-        let sdk = (*rust_sdk).get_sdk();
-
-        tracing::warn!("using existing data contract id and fetching...");
+    rt.block_on(deserialize_document(SdkPtr(rust_sdk), bytes, data_contract_id, document_type))
+}
 
-        let cfg = Config::new();
-        let sdk = cfg.setup_api().await;
+/// Non-blocking version of [deserialize_document_sdk]
+///
+/// Returns the request id, the result is [RequestResult::Document].
+#[ferment_macro::export]
+pub fn deserialize_document_sdk_async(
+    rust_sdk: *mut DashSdk,
+    bytes: Vec<u8>,
+    data_contract_id: Identifier,
+    document_type: String,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        deserialize_document(rust_sdk, bytes, data_contract_id, document_type)
+            .await
+            .map(RequestResult::Document)
+    })
+}
 
-        let contract = match ((*rust_sdk).get_data_contract(&data_contract_id)) {
-            Some(data_contract) => data_contract.clone(),
-            None => {
-                match (DataContract::fetch(&sdk, data_contract_id.clone())
-                    .await) {
-                    Ok(Some(data_contract)) => Arc::new(data_contract),
-                    Ok(None) => return Err("data contract not found".to_string()),
-                    Err(e) => return Err(e.to_string())
-                }
-            }
-        };
+/// Deserialize a document of `document_type`, fetching the data contract if it is not cached
+pub async fn deserialize_document(
+    rust_sdk: SdkPtr,
+    bytes: Vec<u8>,
+    data_contract_id: Identifier,
+    document_type: String
+) -> Result<Document, PlatformError> {
+    let contract = unsafe { (*rust_sdk.0).get_or_fetch_data_contract(&data_contract_id) }.await?;
 
-        Document::from_bytes(&bytes, contract.document_type_for_name(&document_type).unwrap(), LATEST_PLATFORM_VERSION)
-            .or_else(|e| Err(format!("deserialization failed: {}", e.to_string())))
-    })
+    let document_type = contract.document_type_for_name(&document_type)?;
+    Document::from_bytes(&bytes, document_type, LATEST_PLATFORM_VERSION)
+        .or_else(|e| Err(PlatformError::InvalidInput(format!("deserialization failed: {}", e.to_string()))))
 }
 
 
@@ -490,6 +749,42 @@ fn docs_domain_query_sort_test() {
     }
 }
 
+#[test]
+fn document_pager_test() {
+    let mut sdk = create_dash_sdk_using_core_testnet();
+    let contract_id = Identifier::from(dpns_contract::ID_BYTES);
+    let mut pager = create_document_pager(
+        &mut sdk,
+        contract_id,
+        "domain".to_string(),
+        vec![
+            WhereClause { field: "normalizedLabel".into(), value: Value::Text("test".into()), operator: WhereOperator::StartsWith },
+            WhereClause { field: "normalizedParentDomainName".into(), value: Value::Text("dash".into()), operator: WhereOperator::Equal }
+        ],
+        vec![
+            OrderClause { field: "normalizedLabel".into(), ascending: true }
+        ],
+        10
+    ).expect("create pager");
+
+    let mut labels: Vec<Value> = Vec::new();
+    while !document_pager_is_exhausted(&mut pager) {
+        match document_pager_next_page(&mut sdk, &mut pager) {
+            Ok(docs) => {
+                tracing::info!("page results: {}", docs.len());
+                for document in docs {
+                    labels.push(document.properties().get("normalizedLabel").cloned().unwrap_or(Value::Null));
+                }
+            }
+            Err(e) => panic!("{}", e)
+        }
+    }
+    let mut sorted = labels.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    sorted.dedup();
+    assert_eq!(labels, sorted);
+}
+
 #[test]
 fn doc_deserialization_sdk_test() {
     let mut sdk = create_dash_sdk_using_core_testnet();
@@ -820,3 +1115,28 @@ fn check_all_nodes_mainnet_test() {
     println!("good nodes: {:?}", good_nodes);
     println!("bad nodes: {:?}", bad_nodes);
 }
+
+#[test]
+fn document_pager_cursor_test() {
+    use dpp::document::DocumentV0;
+
+    assert_eq!(page_limit(0), MAX_PAGE_SIZE);
+    assert_eq!(page_limit(20), 20);
+    assert_eq!(page_limit(500), MAX_PAGE_SIZE);
+
+    let page = |size: u8| -> Vec<Document> {
+        (0..size).map(|index| Document::V0(DocumentV0 { id: Identifier::from([index; 32]), ..Default::default() })).collect()
+    };
+    let limit = page_limit(500) as usize;
+    let mut cursor = PagerCursor::default();
+    // a full page of 100 documents may be followed by more
+    cursor.advance(&page(100), limit);
+    assert!(!cursor.exhausted);
+    assert_eq!(cursor.last_document_id, Some(Identifier::from([99u8; 32])));
+    cursor.advance(&page(40), limit);
+    assert!(cursor.exhausted);
+
+    let mut cursor = PagerCursor::default();
+    cursor.advance(&page(0), limit);
+    assert!(cursor.exhausted);
+}
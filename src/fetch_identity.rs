@@ -3,17 +3,24 @@ use dpp::identity::identity::Identity;
 use dpp::errors::protocol_error::ProtocolError;
 use platform_version::version::PlatformVersion;
 use dpp::document::{Document, DocumentV0Getters};
-use dash_sdk::platform::{DocumentQuery, Fetch, FetchMany};
+use dpp::identity::accessors::IdentityGettersV0;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use dash_sdk::platform::{DocumentQuery, Fetch, FetchMany, Query};
+use dash_sdk::{RequestSettings, Sdk};
 use dash_sdk::platform::types::identity::PublicKeyHash;
 use dpp::data_contract::DataContract;
 use serde::Deserialize;
 use tokio::runtime::{Runtime, Builder};
+use tokio::sync::Semaphore;
 use dpp::dashcore::PubkeyHash;
 use drive_proof_verifier::types::IdentityBalance;
 use platform_value::string_encoding::Encoding;
 use crate::config::{Config, EntryPoint};
 use crate::logs::setup_logs;
 use crate::sdk::{create_dash_sdk, create_dash_sdk_using_core_testnet, DashSdk};
+use crate::error::PlatformError;
+use crate::health::RequestRecorder;
 
 pub fn test_identifier() -> Identifier {
     Identifier::from_string("7Yowk46VwwHqmD5yZyyygggh937aP6h2UW7aQWBdWpM5", Encoding::Base58).unwrap()
@@ -23,12 +30,12 @@ pub fn test_identifier() -> Identifier {
 pub fn fetch_identity_with_sdk(
     rust_sdk: *mut DashSdk,
     identifier: Identifier
-) -> Result<Identity, String> {
+) -> Result<Identity, PlatformError> {
     tracing::info!("fetch_identity_with_sdk");
     unsafe {
         match identity_read_with_sdk(rust_sdk, &identifier) {
             Ok(identity) => Ok(identity),
-            Err(err) => Err(err.to_string())
+            Err(err) => Err(err)
         }
     }
 }
@@ -37,12 +44,12 @@ pub fn fetch_identity_with_sdk(
 pub fn fetch_identity_balance_with_sdk(
     rust_sdk: *mut DashSdk,
     identifier: Identifier
-) -> Result<u64, String> {
+) -> Result<u64, PlatformError> {
     tracing::info!("fetch_identity_with_sdk");
     unsafe {
         match identity_read_balance_with_sdk(rust_sdk, &identifier) {
             Ok(balance) => Ok(balance),
-            Err(err) => Err(err.to_string())
+            Err(err) => Err(err)
         }
     }
 }
@@ -51,17 +58,17 @@ pub fn fetch_identity_balance_with_sdk(
 pub fn fetch_identity_with_keyhash_sdk(
     rust_sdk: *mut DashSdk,
     key_hash: [u8; 20]
-) -> Result<Identity, String> {
+) -> Result<Identity, PlatformError> {
     tracing::info!("fetch_identity_with_keyhash_sdk");
     unsafe {
         match identity_from_keyhash_sdk(rust_sdk, &PublicKeyHash(key_hash)) {
             Ok(identity) => Ok(identity),
-            Err(err) => Err(err.to_string())
+            Err(err) => Err(err)
         }
     }
 }
 
-unsafe fn identity_read_with_sdk(rust_sdk: *mut DashSdk, id: &Identifier) -> Result<Identity, ProtocolError> {
+unsafe fn identity_read_with_sdk(rust_sdk: *mut DashSdk, id: &Identifier) -> Result<Identity, PlatformError> {
 
     let rt = unsafe { (*rust_sdk).get_runtime() }.clone();
 
@@ -74,19 +81,18 @@ unsafe fn identity_read_with_sdk(rust_sdk: *mut DashSdk, id: &Identifier) -> Res
         tracing::info!("Call fetch");
         let settings = unsafe { (*rust_sdk).get_request_settings() };
         let identity_result = Identity::fetch_with_settings(&sdk, id, settings).await;
+        unsafe { (*rust_sdk).get_request_recorder() }.record_result(&identity_result);
 
         match identity_result {
             Ok(Some(identity)) => Ok(identity),
-            Ok(None) => Err(ProtocolError::IdentifierError("Identity not found".to_string())), // Placeholder for actual error handling
-            Err(e) => Err(ProtocolError::IdentifierError(
-                format!("Identifier not found: failure: {})", e))
-            )
+            Ok(None) => Err(PlatformError::NotFound("Identity not found".to_string())),
+            Err(e) => Err(e.into())
         }
     })
 }
 
 
-unsafe fn identity_read_balance_with_sdk(rust_sdk: *mut DashSdk, id: &Identifier) -> Result<u64, ProtocolError> {
+unsafe fn identity_read_balance_with_sdk(rust_sdk: *mut DashSdk, id: &Identifier) -> Result<u64, PlatformError> {
 
     let rt = unsafe { (*rust_sdk).get_runtime() }.clone();
 
@@ -101,18 +107,17 @@ unsafe fn identity_read_balance_with_sdk(rust_sdk: *mut DashSdk, id: &Identifier
         tracing::info!("Call fetch");
         let settings = unsafe { (*rust_sdk).get_request_settings() };
         let identity_result = IdentityBalance::fetch_with_settings(&sdk, id, settings).await;
+        unsafe { (*rust_sdk).get_request_recorder() }.record_result(&identity_result);
 
         match identity_result {
             Ok(Some(identity)) => Ok(identity),
-            Ok(None) => Err(ProtocolError::IdentifierError("Identity not found".to_string())), // Placeholder for actual error handling
-            Err(e) => Err(ProtocolError::IdentifierError(
-                format!("Identifier not found: failure: {})", e))
-            )
+            Ok(None) => Err(PlatformError::NotFound("Identity not found".to_string())),
+            Err(e) => Err(e.into())
         }
     })
 }
 
-unsafe fn identity_from_keyhash_sdk(rust_sdk: *mut DashSdk, pubkey_hash: &PublicKeyHash) -> Result<Identity, ProtocolError> {
+unsafe fn identity_from_keyhash_sdk(rust_sdk: *mut DashSdk, pubkey_hash: &PublicKeyHash) -> Result<Identity, PlatformError> {
     // Create a new Tokio runtime
     //let rt = tokio::runtime::Runtime::new().expect("Failed to create a runtime");
     let rt = unsafe { (*rust_sdk).get_runtime() };
@@ -128,17 +133,215 @@ unsafe fn identity_from_keyhash_sdk(rust_sdk: *mut DashSdk, pubkey_hash: &Public
         tracing::info!("Call fetch");
         let settings = unsafe { (*rust_sdk).get_request_settings() };
         let identity_result = Identity::fetch_with_settings(&sdk, key_hash, settings).await;
+        unsafe { (*rust_sdk).get_request_recorder() }.record_result(&identity_result);
 
         match identity_result {
             Ok(Some(identity)) => Ok(identity),
-            Ok(None) => Err(ProtocolError::IdentifierError("Identity not found".to_string())), // Placeholder for actual error handling
-            Err(e) => Err(ProtocolError::IdentifierError(
-                format!("Identifier not found: failure: {})", e))
-            )
+            Ok(None) => Err(PlatformError::NotFound("Identity not found".to_string())),
+            Err(e) => Err(e.into())
         }
     })
 }
 
+/// Maximum number of identity requests that are sent at the same time
+const MAX_CONCURRENT_IDENTITY_FETCHES: usize = 8;
+
+/// Result of looking up one identity in a batch
+#[derive(Clone, Debug)]
+#[ferment_macro::export]
+pub enum IdentityLookup {
+    /// The identity exists
+    Found(Identity),
+    /// Platform returned a proof that the identity does not exist
+    ProvedAbsent,
+    /// The request for this identity failed, so it is not known whether it exists
+    Failed(PlatformError),
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn IdentityLookup_clone(value: IdentityLookup) -> IdentityLookup {
+    value.clone()
+}
+
+// fetch identities with bounded concurrency and record failures in `recorder`.  Identity does
+// not implement FetchMany: Platform has no query for many identities by id or by public key
+// hash, so every identity is a separate request.
+async fn fetch_identities_concurrently<K, Q>(
+    sdk: Arc<Sdk>,
+    settings: RequestSettings,
+    recorder: RequestRecorder,
+    keys: Vec<K>,
+    to_query: fn(&K) -> Q
+) -> BTreeMap<K, IdentityLookup>
+    where
+        K: Ord + Clone + Send + 'static,
+        Q: Query<<Identity as Fetch>::Request> + Send + 'static
+{
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_IDENTITY_FETCHES));
+    let mut handles = vec![];
+
+    for key in keys {
+        let sdk = sdk.clone();
+        let semaphore = semaphore.clone();
+        let recorder = recorder.clone();
+        let query = to_query(&key);
+        let handle = tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result = Identity::fetch_with_settings(&sdk, query, settings).await;
+            recorder.record_result(&result);
+            result
+        });
+        handles.push((key, handle));
+    }
+
+    let mut results = BTreeMap::new();
+    for (key, handle) in handles {
+        let lookup = match handle.await {
+            Ok(Ok(Some(identity))) => IdentityLookup::Found(identity),
+            Ok(Ok(None)) => IdentityLookup::ProvedAbsent,
+            Ok(Err(e)) => {
+                tracing::warn!("fetch_identities_concurrently, response error: {:?}", e);
+                IdentityLookup::Failed(e.into())
+            }
+            Err(e) => {
+                tracing::warn!("fetch_identities_concurrently, join error: {:?}", e);
+                IdentityLookup::Failed(PlatformError::Generic(e.to_string()))
+            }
+        };
+        results.insert(key, lookup);
+    }
+    results
+}
+
+/// Fetch many identities by id
+///
+/// Every id is in the result, so that identities that do not exist can be told
+/// apart from those that could not be fetched.
+#[ferment_macro::export]
+pub fn fetch_identities_with_sdk(
+    rust_sdk: *mut DashSdk,
+    ids: Vec<Identifier>
+) -> BTreeMap<Identifier, IdentityLookup> {
+    tracing::info!("fetch_identities_with_sdk: {} ids", ids.len());
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+
+    rt.block_on(async {
+        let sdk = unsafe { (*rust_sdk).get_sdk() };
+        let settings = unsafe { (*rust_sdk).get_request_settings() };
+        let recorder = unsafe { (*rust_sdk).get_request_recorder() };
+        fetch_identities_concurrently(sdk, settings, recorder, ids, |id| id.clone()).await
+    })
+}
+
+/// Fetch many identities by the hash of one of their unique public keys
+#[ferment_macro::export]
+pub fn fetch_identities_by_keyhashes_sdk(
+    rust_sdk: *mut DashSdk,
+    key_hashes: Vec<[u8; 20]>
+) -> BTreeMap<[u8; 20], IdentityLookup> {
+    tracing::info!("fetch_identities_by_keyhashes_sdk: {} key hashes", key_hashes.len());
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+
+    rt.block_on(async {
+        let sdk = unsafe { (*rust_sdk).get_sdk() };
+        let settings = unsafe { (*rust_sdk).get_request_settings() };
+        let recorder = unsafe { (*rust_sdk).get_request_recorder() };
+        fetch_identities_concurrently(sdk, settings, recorder, key_hashes, |key_hash| PublicKeyHash(*key_hash)).await
+    })
+}
+
+/// Derives the hash160 of the authentication key `key_index` of the identity at `identity_index`
+/// and writes the 20 bytes to `result`.  Returns 0 if the key could not be derived.
+pub type KeyHashDerivationCallback = extern "C" fn(context: usize, identity_index: u32, key_index: u32, result: * mut u8) -> u32;
+
+/// An identity found during discovery with the identity index of the wallet that owns it
+#[derive(Clone, Debug)]
+#[ferment_macro::export]
+pub struct DiscoveredIdentity {
+    pub index: u32,
+    pub identity: Identity,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn DiscoveredIdentity_clone(value: DiscoveredIdentity) -> DiscoveredIdentity {
+    value.clone()
+}
+
+/// Find the identities of a wallet by scanning identity indexes (DIP-13)
+///
+/// The key hashes of the first `keys_per_identity` authentication keys of each identity index are
+/// derived with `key_hash_callback` and looked up on Platform.  Scanning stops after `gap_limit`
+/// consecutive identity indexes without an identity.
+#[ferment_macro::export]
+pub fn discover_identities_sdk(
+    rust_sdk: *mut DashSdk,
+    gap_limit: u32,
+    keys_per_identity: u32,
+    key_hash_context: usize,
+    key_hash_callback: u64
+) -> Result<Vec<DiscoveredIdentity>, PlatformError> {
+    if gap_limit == 0 {
+        return Err(PlatformError::InvalidInput("gap_limit must be greater than 0".to_string()));
+    }
+    let keys_per_identity = keys_per_identity.max(1);
+    let callback: KeyHashDerivationCallback = unsafe { std::mem::transmute(key_hash_callback as usize) };
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+
+    rt.block_on(async {
+        let sdk = unsafe { (*rust_sdk).get_sdk() };
+        let settings = unsafe { (*rust_sdk).get_request_settings() };
+        let recorder = unsafe { (*rust_sdk).get_request_recorder() };
+        let mut discovered: Vec<DiscoveredIdentity> = vec![];
+        let mut next_index = 0u32;
+        let mut scan_end = gap_limit;
+
+        while next_index < scan_end {
+            // derive the key hashes for the rest of the gap window
+            let mut key_hashes = vec![];
+            for identity_index in next_index..scan_end {
+                for key_index in 0..keys_per_identity {
+                    let mut key_hash = [0u8; 20];
+                    if callback(key_hash_context, identity_index, key_index, key_hash.as_mut_ptr()) == 0 {
+                        return Err(PlatformError::InvalidInput(
+                            format!("failed to derive key {} of identity {}", key_index, identity_index)
+                        ));
+                    }
+                    key_hashes.push((identity_index, key_hash));
+                }
+            }
+            tracing::info!("discover_identities_sdk: scanning identity indexes {}..{}", next_index, scan_end);
+
+            let results = fetch_identities_concurrently(
+                sdk.clone(),
+                settings,
+                recorder.clone(),
+                key_hashes.iter().map(|(_, key_hash)| *key_hash).collect(),
+                |key_hash| PublicKeyHash(*key_hash)
+            ).await;
+
+            next_index = scan_end;
+            for (identity_index, key_hash) in key_hashes {
+                match results.get(&key_hash) {
+                    Some(IdentityLookup::Found(identity)) => {
+                        if !discovered.iter().any(|found| found.identity.id() == identity.id()) {
+                            discovered.push(DiscoveredIdentity { index: identity_index, identity: identity.clone() });
+                        }
+                        scan_end = scan_end.max(identity_index.saturating_add(gap_limit).saturating_add(1));
+                    }
+                    Some(IdentityLookup::Failed(e)) => {
+                        // a failed lookup could hide an identity, so the gap can't be trusted
+                        return Err(e.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(discovered)
+    })
+}
+
 #[test]
 fn fetch_identity_with_sdk_test() {
     let mut rust_sdk = create_dash_sdk_using_core_testnet();
@@ -163,4 +366,45 @@ fn fetch_identity_balance_with_sdk_test() {
         Ok(balance) => tracing::info!("success fetching identity: {:?}", balance),
         Err(err) => panic!("error fetching identity: {}", err)
     }
-}
\ No newline at end of file
+}
+#[test]
+fn fetch_identities_with_sdk_test() {
+    let mut rust_sdk = create_dash_sdk_using_core_testnet();
+    let missing_identifier = Identifier::from_bytes(&[0u8; 32]).unwrap();
+    let result = fetch_identities_with_sdk(
+        &mut rust_sdk,
+        vec![test_identifier(), missing_identifier]
+    );
+    assert_eq!(result.len(), 2);
+    match result.get(&test_identifier()) {
+        Some(IdentityLookup::Found(identity)) => tracing::info!("success fetching identity: {:?}", identity),
+        other => panic!("error fetching identity: {:?}", other)
+    }
+    match result.get(&missing_identifier) {
+        Some(IdentityLookup::ProvedAbsent) => {},
+        other => panic!("expected missing identity: {:?}", other)
+    }
+}
+
+#[test]
+fn discover_identities_sdk_test() {
+    extern "C" fn derive_unused_key_hash(_context: usize, identity_index: u32, key_index: u32, result: * mut u8) -> u32 {
+        let mut key_hash = [0xffu8; 20];
+        key_hash[..4].copy_from_slice(&identity_index.to_be_bytes());
+        key_hash[4..8].copy_from_slice(&key_index.to_be_bytes());
+        unsafe { std::ptr::copy_nonoverlapping(key_hash.as_ptr(), result, 20) };
+        20
+    }
+    let mut rust_sdk = create_dash_sdk_using_core_testnet();
+    let result = discover_identities_sdk(
+        &mut rust_sdk,
+        5,
+        2,
+        0,
+        derive_unused_key_hash as usize as u64
+    );
+    match result {
+        Ok(identities) => assert!(identities.is_empty()),
+        Err(e) => panic!("{}", e)
+    }
+}
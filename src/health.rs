@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use dapi_grpc::platform::v0::ResponseMetadata;
+use dash_sdk::{Error, RequestSettings, Sdk};
+use dash_sdk::platform::{DocumentQuery, Fetch, FetchMany};
+use rs_dapi_client::DapiClientError;
+use rs_dapi_client::transport::TransportError;
+use dpp::data_contract::DataContract;
+use dpp::document::Document;
+use drive::query::{OrderClause, WhereClause, WhereOperator};
+use http::Uri;
+use parking_lot::Mutex;
+use platform_value::{Identifier, Value};
+use crate::config::PlatformNetwork;
+use crate::error::PlatformError;
+use crate::probe::NodeProbeQuery;
+use crate::evonodes::EvonodeRefresher;
+use crate::sdk::{DashSdk, DashSdkOptions};
+
+/// Nodes are banned for this long after the first failure, the period doubles
+/// with each consecutive failure
+const BASE_BAN_PERIOD: Duration = Duration::from_secs(30);
+const MAX_BAN_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// Kind of the last error returned by a node
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub enum NodeErrorKind {
+    Timeout,
+    Unavailable,
+    ProofVerificationFailed,
+    Other,
+}
+
+impl From<&PlatformError> for NodeErrorKind {
+    fn from(error: &PlatformError) -> Self {
+        match error {
+            PlatformError::Timeout(_) => NodeErrorKind::Timeout,
+            PlatformError::AllNodesBanned(_) => NodeErrorKind::Unavailable,
+            PlatformError::ProofVerificationFailed(_) => NodeErrorKind::ProofVerificationFailed,
+            _ => NodeErrorKind::Other
+        }
+    }
+}
+
+/// Health of one evonode, as returned by [get_node_health_report]
+#[derive(Clone, Debug)]
+#[ferment_macro::export]
+pub struct NodeHealth {
+    pub address: String,
+    pub successes: u32,
+    pub failures: u32,
+    pub proof_failures: u32,
+    pub consecutive_failures: u32,
+    /// Moving average of the response time of successful requests
+    pub average_latency_ms: u64,
+    pub last_error_kind: Option<NodeErrorKind>,
+    pub last_error: Option<String>,
+    /// Time left until the node is used again, 0 if it is not banned
+    pub banned_for_ms: u64,
+    /// Higher is better
+    pub score: f64,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn NodeHealth_clone(value: NodeHealth) -> NodeHealth {
+    value.clone()
+}
+
+#[derive(Default)]
+struct NodeState {
+    successes: u32,
+    failures: u32,
+    proof_failures: u32,
+    consecutive_failures: u32,
+    average_latency_ms: Option<u64>,
+    last_error_kind: Option<NodeErrorKind>,
+    last_error: Option<String>,
+    banned_until: Option<Instant>,
+    last_checked: Option<Instant>,
+}
+
+impl NodeState {
+    fn is_banned(&self, now: Instant) -> bool {
+        self.banned_until.map_or(false, |banned_until| banned_until > now)
+    }
+
+    // success rate with one success and one failure added so that unknown nodes
+    // are in the middle, divided by the latency
+    fn score(&self) -> f64 {
+        let success_rate = (self.successes as f64 + 1.0) / ((self.successes + self.failures) as f64 + 2.0);
+        let latency_ms = self.average_latency_ms.unwrap_or(1000) as f64;
+        success_rate * 1000.0 / (latency_ms + 100.0)
+    }
+}
+
+fn ban_period(consecutive_failures: u32) -> Duration {
+    let multiplier = 1u32 << consecutive_failures.saturating_sub(1).min(16);
+    BASE_BAN_PERIOD.saturating_mul(multiplier).min(MAX_BAN_PERIOD)
+}
+
+/// Records the result of requests to each evonode, bans nodes that fail with an
+/// exponential backoff and ranks the remaining nodes by success rate and latency
+#[derive(Default)]
+pub struct NodeHealthTracker {
+    nodes: Mutex<HashMap<String, NodeState>>,
+}
+
+impl NodeHealthTracker {
+    pub fn record_success(&self, address: &str, latency: Duration) {
+        let mut nodes = self.nodes.lock();
+        let node = nodes.entry(address.to_string()).or_default();
+        let latency_ms = latency.as_millis() as u64;
+        node.successes += 1;
+        node.consecutive_failures = 0;
+        node.banned_until = None;
+        node.average_latency_ms = Some(match node.average_latency_ms {
+            Some(average) => (average * 3 + latency_ms) / 4,
+            None => latency_ms
+        });
+        node.last_checked = Some(Instant::now());
+    }
+
+    pub fn record_failure(&self, address: &str, error: &PlatformError) {
+        let mut nodes = self.nodes.lock();
+        let node = nodes.entry(address.to_string()).or_default();
+        let kind = NodeErrorKind::from(error);
+        node.failures += 1;
+        if kind == NodeErrorKind::ProofVerificationFailed {
+            node.proof_failures += 1;
+        }
+        node.consecutive_failures += 1;
+        node.last_error_kind = Some(kind);
+        node.last_error = Some(error.to_string());
+        let now = Instant::now();
+        node.banned_until = Some(now + ban_period(node.consecutive_failures));
+        node.last_checked = Some(now);
+        tracing::info!("NodeHealthTracker: {} banned for {:?}: {}", address, ban_period(node.consecutive_failures), error);
+    }
+
+    /// The nodes of `addresses` that are not banned, best first.  At most `count` nodes are
+    /// returned unless `count` is 0.  When every node is banned, all of them are returned so
+    /// that the SDK always has nodes to try.
+    ///
+    /// Nodes with the same score keep their order in `active`, then in `addresses`, so that
+    /// the [Sdk](dash_sdk::Sdk) is not rebuilt while the scores are unchanged.
+    pub fn preferred_nodes(&self, addresses: &Vec<String>, active: &Vec<String>, count: usize) -> Vec<String> {
+        let nodes = self.nodes.lock();
+        let now = Instant::now();
+        let mut available: Vec<(f64, usize, &String)> = addresses.iter()
+            .enumerate()
+            .filter_map(|(index, address)| {
+                let rank = active.iter().position(|a| a == address).unwrap_or(active.len() + index);
+                match nodes.get(address) {
+                    Some(node) if node.is_banned(now) => None,
+                    Some(node) => Some((node.score(), rank, address)),
+                    None => Some((NodeState::default().score(), rank, address))
+                }
+            })
+            .collect();
+        if available.is_empty() {
+            tracing::warn!("NodeHealthTracker: all nodes are banned");
+            return addresses.clone();
+        }
+        available.sort_by(|a, b| {
+            b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal).then(a.1.cmp(&b.1))
+        });
+        if count > 0 {
+            available.truncate(count);
+        }
+        available.into_iter().map(|(_, _, address)| address.clone()).collect()
+    }
+
+    /// The nodes of `addresses` that should be checked next: never checked nodes first,
+    /// then the ones that were checked the longest time ago
+    pub fn nodes_to_check(&self, addresses: &Vec<String>, count: usize) -> Vec<String> {
+        let nodes = self.nodes.lock();
+        let mut candidates: Vec<(Option<Instant>, &String)> = addresses.iter()
+            .map(|address| (nodes.get(address).and_then(|node| node.last_checked), address))
+            .collect();
+        candidates.sort_by_key(|(last_checked, _)| *last_checked);
+        candidates.into_iter().take(count).map(|(_, address)| address.clone()).collect()
+    }
+
+    pub fn report(&self) -> Vec<NodeHealth> {
+        let nodes = self.nodes.lock();
+        let now = Instant::now();
+        let mut report: Vec<NodeHealth> = nodes.iter().map(|(address, node)| NodeHealth {
+            address: address.clone(),
+            successes: node.successes,
+            failures: node.failures,
+            proof_failures: node.proof_failures,
+            consecutive_failures: node.consecutive_failures,
+            average_latency_ms: node.average_latency_ms.unwrap_or_default(),
+            last_error_kind: node.last_error_kind,
+            last_error: node.last_error.clone(),
+            banned_for_ms: node.banned_until
+                .map(|banned_until| banned_until.saturating_duration_since(now).as_millis() as u64)
+                .unwrap_or_default(),
+            score: node.score(),
+        }).collect();
+        report.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        report
+    }
+}
+
+/// Records requests of the SDK that failed in a [NodeHealthTracker]
+///
+/// The DAPI client only reports the evonode of transport errors, so the evonodes
+/// of successful requests are not known and only probes record successes.
+#[derive(Clone)]
+pub struct RequestRecorder {
+    health: Arc<NodeHealthTracker>,
+    default_port: u16,
+}
+
+impl RequestRecorder {
+    pub fn new(health: Arc<NodeHealthTracker>, network: &PlatformNetwork) -> Self {
+        RequestRecorder {
+            health,
+            default_port: network.default_platform_port(),
+        }
+    }
+
+    /// Record `error` as a failure of the evonode that returned it, if the DAPI client reported one
+    pub fn record_error(&self, error: &Error) {
+        if let Error::DapiClientError(message) = error {
+            if let Some(address) = evonode_of_dapi_error(message, self.default_port) {
+                self.health.record_failure(&address, &PlatformError::from(Error::DapiClientError(message.clone())));
+            }
+        }
+    }
+
+    /// Record `error` of a request that was executed directly with the DAPI client
+    pub fn record_dapi_error(&self, error: &DapiClientError<TransportError>) {
+        if let DapiClientError::Transport(_, address) = error {
+            if let Some(address) = evonode_of_uri(address.uri(), self.default_port) {
+                self.health.record_failure(&address, &PlatformError::from(error));
+            }
+        }
+    }
+
+    pub fn record_result<T>(&self, result: &Result<T, Error>) {
+        if let Err(error) = result {
+            self.record_error(error);
+        }
+    }
+}
+
+// dash_sdk::Error has the debug output of DapiClientError::Transport, which ends with the
+// address of the evonode, such as `Address { ban_count: 0, banned_until: None, uri: https://35.165.50.126:1443/ }`
+fn evonode_of_dapi_error(message: &str, default_port: u16) -> Option<String> {
+    let start = message.rfind("uri: ")? + "uri: ".len();
+    let uri: String = message[start..].chars()
+        .take_while(|c| !c.is_whitespace() && *c != ',' && *c != '}' && *c != ')')
+        .collect();
+    evonode_of_uri(&Uri::from_str(&uri).ok()?, default_port)
+}
+
+// the address of an evonode without the port if it is the default port
+fn evonode_of_uri(uri: &Uri, default_port: u16) -> Option<String> {
+    let host = uri.host()?;
+    Some(match uri.port_u16() {
+        Some(port) if port != default_port => format!("{}:{}", host, port),
+        _ => host.to_string()
+    })
+}
+
+/// Send `query` to a single evonode and return the time it took and the metadata of the response
+///
+/// The node does not get more than the connect timeout and two request timeouts to respond.
+pub async fn check_node(
+    refresher: &EvonodeRefresher,
+    address: &str,
+    query: NodeProbeQuery
+) -> Result<(Duration, ResponseMetadata), PlatformError> {
+    let options = DashSdkOptions {
+        address_list: vec![address.to_string()],
+        retries: 0,
+        ban_failed_address: false,
+        ..refresher.options.clone()
+    };
+    let settings = options.request_settings();
+    let timeout = settings.connect_timeout.unwrap_or_default() + settings.timeout.unwrap_or_default() * 2;
+    let sdk = refresher.config.setup_api_with_options(
+        &options,
+        refresher.data_contract_cache.clone(),
+        refresher.quorum_public_keys_cache.clone()
+    ).await;
+
+    let started = Instant::now();
+    match tokio::time::timeout(timeout, run_probe_query(&sdk, settings, query)).await {
+        Ok(result) => result.map(|metadata| (started.elapsed(), metadata)),
+        Err(_) => Err(PlatformError::Timeout(format!("no response after {:?}", timeout)))
+    }
+}
+
+async fn run_probe_query(
+    sdk: &Sdk,
+    settings: RequestSettings,
+    query: NodeProbeQuery
+) -> Result<ResponseMetadata, PlatformError> {
+    let (data_contract, metadata) = DataContract::fetch_with_metadata(
+        sdk,
+        Identifier::from(dpns_contract::ID_BYTES),
+        Some(settings)
+    ).await?;
+    let data_contract = data_contract.ok_or(PlatformError::ProofVerificationFailed(
+        "the DPNS contract was proved to not exist".to_string()
+    ))?;
+
+    if query == NodeProbeQuery::DpnsDomains {
+        let mut domain_query = DocumentQuery::new(Arc::new(data_contract), "domain")?
+            .with_where(WhereClause {
+                field: "normalizedParentDomainName".into(),
+                value: Value::Text("dash".into()),
+                operator: WhereOperator::Equal
+            })
+            .with_order_by(OrderClause { field: "normalizedLabel".into(), ascending: true });
+        domain_query.limit = 1;
+        Document::fetch_many_with_settings(sdk, domain_query, settings).await?;
+    }
+    Ok(metadata)
+}
+
+/// Get the health of every evonode that has been used, best first
+#[ferment_macro::export]
+pub fn get_node_health_report(rust_sdk: *mut DashSdk) -> Vec<NodeHealth> {
+    unsafe { (*rust_sdk).get_evonode_refresher().health.report() }
+}
+
+#[test]
+fn node_health_tracker_test() {
+    let tracker = NodeHealthTracker::default();
+    let addresses = vec!["fast".to_string(), "slow".to_string(), "broken".to_string()];
+    tracker.record_success("fast", Duration::from_millis(50));
+    tracker.record_success("slow", Duration::from_millis(2000));
+    tracker.record_failure("broken", &PlatformError::Timeout("test".to_string()));
+
+    assert_eq!(tracker.preferred_nodes(&addresses, &vec![], 0), vec!["fast".to_string(), "slow".to_string()]);
+    assert_eq!(tracker.preferred_nodes(&addresses, &vec![], 1), vec!["fast".to_string()]);
+
+    tracker.record_failure("fast", &PlatformError::Timeout("test".to_string()));
+    tracker.record_failure("slow", &PlatformError::ProofVerificationFailed("test".to_string()));
+    // every node is banned, so all of them are used
+    assert_eq!(tracker.preferred_nodes(&addresses, &vec![], 0), addresses);
+
+    let report = tracker.report();
+    assert_eq!(report.len(), 3);
+    assert_eq!(report.iter().find(|node| node.address == "slow").unwrap().proof_failures, 1);
+    assert_eq!(ban_period(1), BASE_BAN_PERIOD);
+    assert_eq!(ban_period(2), BASE_BAN_PERIOD * 2);
+    assert_eq!(ban_period(100), MAX_BAN_PERIOD);
+}
+
+#[test]
+fn preferred_nodes_ties_test() {
+    let tracker = NodeHealthTracker::default();
+    let addresses: Vec<String> = (1..=5).map(|i| format!("10.0.0.{}", i)).collect();
+    // unknown nodes keep their order, so the same nodes are preferred every time
+    let preferred = tracker.preferred_nodes(&addresses, &vec![], 2);
+    assert_eq!(preferred, vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()]);
+    assert_eq!(tracker.preferred_nodes(&addresses, &vec![], 2), preferred);
+    // the active nodes are kept while the scores are equal
+    let active = vec!["10.0.0.4".to_string(), "10.0.0.5".to_string()];
+    assert_eq!(tracker.preferred_nodes(&addresses, &active, 2), active);
+}
+
+#[test]
+fn request_recorder_test() {
+    let health = Arc::new(NodeHealthTracker::default());
+    let recorder = RequestRecorder::new(health.clone(), &PlatformNetwork::Testnet);
+    recorder.record_error(&Error::DapiClientError(
+        "Transport(Status { code: Unavailable, message: \"error trying to connect\" }, \
+        Address { ban_count: 0, banned_until: None, uri: https://35.165.50.126:1443/ })".to_string()
+    ));
+    recorder.record_error(&Error::DapiClientError(
+        "Transport(Status { code: DeadlineExceeded, message: \"timeout\" }, \
+        Address { ban_count: 0, banned_until: None, uri: http://127.0.0.1:2543/ })".to_string()
+    ));
+    recorder.record_error(&Error::DapiClientError("NoAvailableAddresses".to_string()));
+    recorder.record_result::<()>(&Err(Error::Generic("not a transport error".to_string())));
+    recorder.record_dapi_error(&DapiClientError::Transport(
+        TransportError::Grpc(dapi_grpc::tonic::Status::deadline_exceeded("timeout")),
+        rs_dapi_client::Address::from_str("https://35.165.50.127:1443").unwrap()
+    ));
+
+    let report = health.report();
+    assert_eq!(report.len(), 3);
+    assert!(report.iter().any(|node| node.address == "35.165.50.126" && node.failures == 1));
+    // the kind of error is only known for typed DAPI client errors
+    assert!(report.iter().any(|node| node.address == "127.0.0.1:2543"
+        && node.last_error_kind == Some(NodeErrorKind::Other)));
+    assert!(report.iter().any(|node| node.address == "35.165.50.127"
+        && node.last_error_kind == Some(NodeErrorKind::Timeout)));
+}
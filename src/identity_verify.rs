@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use dpp::document::{Document, DocumentV0Getters, DocumentV0Setters};
+use dpp::identity::identity_public_key::IdentityPublicKey;
+use drive::query::{WhereClause, WhereOperator};
+use platform_value::{Identifier, Value};
+use crate::config::EntryPoint;
+use crate::dpns::{DPNS_PARENT_DOMAIN, get_text_property, new_document, normalize_dpns_label};
+use crate::error::PlatformError;
+use crate::fetch_document::fetch_documents_with_query_and_sdk;
+use crate::put::{CallbackSigner, delete_document_from_platform_and_wait, put_document_to_platform, replace_document_on_platform};
+use crate::request::SdkPtr;
+use crate::sdk::DashSdk;
+
+pub const IDENTITY_VERIFY_DOCUMENT_TYPE: &str = "identityVerify";
+/// `maxLength` of `url` in the dashwallet contract
+pub const IDENTITY_VERIFY_URL_MAX_LENGTH: usize = 128;
+
+/// An `identityVerify` document of the dashwallet contract, which links a username
+/// to a page that verifies who owns it
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub struct IdentityVerifyInfo {
+    pub id: Identifier,
+    pub owner_id: Identifier,
+    pub normalized_label: String,
+    pub normalized_parent_domain_name: String,
+    pub url: String,
+    pub revision: Option<u64>,
+    pub updated_at: Option<u64>,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn IdentityVerifyInfo_clone(value: IdentityVerifyInfo) -> IdentityVerifyInfo {
+    value.clone()
+}
+
+impl From<Document> for IdentityVerifyInfo {
+    fn from(document: Document) -> Self {
+        IdentityVerifyInfo {
+            id: document.id(),
+            owner_id: document.owner_id(),
+            normalized_label: get_text_property(&document, "normalizedLabel"),
+            normalized_parent_domain_name: get_text_property(&document, "normalizedParentDomainName"),
+            url: get_text_property(&document, "url"),
+            revision: document.revision(),
+            updated_at: document.updated_at().or(document.created_at()),
+        }
+    }
+}
+
+/// Check that `url` matches the `url` property of `identityVerify`: it must start with
+/// `http://` or `https://`, have a host and be at most 128 characters long.
+#[ferment_macro::export]
+pub fn validate_identity_verify_url(url: String) -> Result<String, PlatformError> {
+    let length = url.chars().count();
+    if length > IDENTITY_VERIFY_URL_MAX_LENGTH {
+        return Err(PlatformError::InvalidInput(
+            format!("url must be at most {} characters: {}", IDENTITY_VERIFY_URL_MAX_LENGTH, url)
+        ));
+    }
+    let rest = match url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) {
+        Some(rest) => rest,
+        None => return Err(PlatformError::InvalidInput(format!("url must start with http:// or https://: {}", url)))
+    };
+    if url.chars().any(char::is_whitespace) {
+        return Err(PlatformError::InvalidInput(format!("url cannot contain spaces: {}", url)));
+    }
+    if rest.split(|c| c == '/' || c == '?' || c == '#').next().unwrap_or_default().is_empty() {
+        return Err(PlatformError::InvalidInput(format!("url has no host: {}", url)));
+    }
+    Ok(url)
+}
+
+/// Get the fields of an `identityVerify` document
+#[ferment_macro::export]
+pub fn identity_verify_info(document: Document) -> IdentityVerifyInfo {
+    document.into()
+}
+
+// a label without a parent domain is under `dash`
+fn normalized_parent_domain_name(parent_domain_name: &str) -> String {
+    if parent_domain_name.is_empty() {
+        DPNS_PARENT_DOMAIN.to_string()
+    } else {
+        normalize_dpns_label(parent_domain_name.to_string())
+    }
+}
+
+/// Get the `identityVerify` document of `owner_id` for `label`.`parent_domain_name`
+#[ferment_macro::export]
+pub fn fetch_identity_verify_sdk(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    owner_id: Identifier,
+    label: String,
+    parent_domain_name: String
+) -> Result<Option<Document>, PlatformError> {
+    let documents = fetch_documents_with_query_and_sdk(
+        rust_sdk,
+        data_contract_id,
+        IDENTITY_VERIFY_DOCUMENT_TYPE.to_string(),
+        vec![
+            WhereClause { field: "$ownerId".into(), value: Value::Identifier(owner_id.to_buffer()), operator: WhereOperator::Equal },
+            WhereClause { field: "normalizedParentDomainName".into(), value: Value::Text(normalized_parent_domain_name(&parent_domain_name)), operator: WhereOperator::Equal },
+            WhereClause { field: "normalizedLabel".into(), value: Value::Text(normalize_dpns_label(label)), operator: WhereOperator::Equal }
+        ],
+        vec![],
+        1,
+        None
+    )?;
+    Ok(documents.into_iter().next())
+}
+
+/// List the `identityVerify` documents for `label`, from every owner
+#[ferment_macro::export]
+pub fn fetch_identity_verify_by_label_sdk(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    label: String
+) -> Result<Vec<IdentityVerifyInfo>, PlatformError> {
+    let documents = fetch_documents_with_query_and_sdk(
+        rust_sdk,
+        data_contract_id,
+        IDENTITY_VERIFY_DOCUMENT_TYPE.to_string(),
+        vec![
+            WhereClause { field: "normalizedLabel".into(), value: Value::Text(normalize_dpns_label(label)), operator: WhereOperator::Equal }
+        ],
+        vec![],
+        100,
+        None
+    )?;
+    Ok(documents.into_iter().map(IdentityVerifyInfo::from).collect())
+}
+
+/// Create the `identityVerify` document of `owner_id` for `label`.`parent_domain_name`
+///
+/// Use [update_identity_verify_sdk] to change the url of an existing document.
+#[ferment_macro::export]
+pub fn create_identity_verify_sdk(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    owner_id: Identifier,
+    label: String,
+    parent_domain_name: String,
+    url: String,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<Document, PlatformError> {
+    let url = validate_identity_verify_url(url)?;
+    let mut properties: BTreeMap<String, Value> = BTreeMap::new();
+    properties.insert("normalizedLabel".to_string(), Value::Text(normalize_dpns_label(label)));
+    properties.insert("normalizedParentDomainName".to_string(), Value::Text(normalized_parent_domain_name(&parent_domain_name)));
+    properties.insert("url".to_string(), Value::Text(url));
+
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    rt.block_on(async {
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        put_document_to_platform(
+            SdkPtr(rust_sdk),
+            new_document(owner_id, properties),
+            data_contract_id,
+            IDENTITY_VERIFY_DOCUMENT_TYPE.to_string(),
+            identity_public_key,
+            0,
+            0,
+            signer
+        ).await
+    })
+}
+
+/// Replace the url of an `identityVerify` document
+#[ferment_macro::export]
+pub fn update_identity_verify_sdk(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    document: Document,
+    url: String,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<Document, PlatformError> {
+    let url = validate_identity_verify_url(url)?;
+    let mut document = document;
+    document.properties_mut().insert("url".to_string(), Value::Text(url));
+    document.set_revision(Some(document.revision().unwrap_or(1) + 1));
+
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    rt.block_on(async {
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        replace_document_on_platform(
+            SdkPtr(rust_sdk),
+            document,
+            data_contract_id,
+            IDENTITY_VERIFY_DOCUMENT_TYPE.to_string(),
+            identity_public_key,
+            signer
+        ).await
+    })
+}
+
+/// Delete an `identityVerify` document, returns its id
+#[ferment_macro::export]
+pub fn delete_identity_verify_sdk(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    document: Document,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<Identifier, PlatformError> {
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    rt.block_on(async {
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        delete_document_from_platform_and_wait(
+            SdkPtr(rust_sdk),
+            document,
+            data_contract_id,
+            IDENTITY_VERIFY_DOCUMENT_TYPE.to_string(),
+            identity_public_key,
+            signer
+        ).await
+    })
+}
+
+#[test]
+fn validate_identity_verify_url_test() {
+    assert!(validate_identity_verify_url("https://twitter.com/dashpay".to_string()).is_ok());
+    assert!(validate_identity_verify_url("http://example.com".to_string()).is_ok());
+    assert!(validate_identity_verify_url("ftp://example.com".to_string()).is_err());
+    assert!(validate_identity_verify_url("HTTPS://example.com".to_string()).is_err());
+    assert!(validate_identity_verify_url("https://".to_string()).is_err());
+    assert!(validate_identity_verify_url("https://example.com/a b".to_string()).is_err());
+    let long_url = format!("https://example.com/{}", "a".repeat(IDENTITY_VERIFY_URL_MAX_LENGTH));
+    assert!(validate_identity_verify_url(long_url).is_err());
+}
@@ -13,6 +13,18 @@ pub mod core;
 mod logs;
 pub mod voting;
 pub mod sdk;
+pub mod error;
+pub mod dpns;
+pub mod credits;
+pub mod request;
+pub mod evonodes;
+pub mod health;
+pub mod probe;
+pub mod disk_cache;
+pub mod tx_metadata;
+pub mod identity_verify;
+pub mod encryption;
+pub mod dashpay;
 
 extern crate ferment_macro;
 
@@ -62,9 +62,17 @@ impl<S> Layer<S> for AndroidLogLayer
     }
 }
 
+pub const DEFAULT_LOG_FILTER: &str = "info,dash_sdk=trace,h2=info";
+
+pub fn setup_logs() {
+    setup_logs_with_filter(DEFAULT_LOG_FILTER)
+}
+
 static mut LOGS_SETUP: bool = false;
+/// Set up logging with a tracing filter such as `info,dash_sdk=trace`.
+/// Only the first call has an effect.
 #[cfg(target_os = "android")]
-pub fn setup_logs() {
+pub fn setup_logs_with_filter(filter: &str) {
     unsafe {
         if (LOGS_SETUP) {
             return
@@ -76,7 +84,7 @@ pub fn setup_logs() {
         Ok(filter) => filter,
         Err(e) => {
             // android_log_message("platform-mobile", &format!("Error parsing env filter: {}. Using default.", e));
-            tracing_subscriber::EnvFilter::new("info,dash_sdk=trace,h2=info")
+            tracing_subscriber::EnvFilter::new(filter)
         }
     };
     let subscriber = tracing_subscriber::registry()
@@ -99,11 +107,9 @@ pub fn setup_logs() {
 }
 
 #[cfg(not(target_os = "android"))]
-pub fn setup_logs() {
+pub fn setup_logs_with_filter(filter: &str) {
     tracing_subscriber::fmt::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::new(
-            "info,dash_sdk=trace,h2=info",
-        ))
+        .with_env_filter(tracing_subscriber::EnvFilter::new(filter))
         .pretty()
         .with_ansi(false)
         .with_writer(std::io::stdout)
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+use crate::config::EntryPoint;
+use crate::evonodes::EvonodeRefresher;
+use crate::health::{check_node, NodeErrorKind};
+use crate::sdk::{create_dash_sdk_using_core_testnet, DashSdk};
+
+/// Maximum number of evonodes that are probed at the same time
+const MAX_CONCURRENT_PROBES: usize = 16;
+
+/// The request that is sent to each evonode by [probe_evonodes]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[ferment_macro::export]
+pub enum NodeProbeQuery {
+    /// Fetch the DPNS contract
+    DataContract,
+    /// Fetch the DPNS contract, then query the first `dash` domain
+    DpnsDomains,
+}
+
+/// Result of probing one evonode
+#[derive(Clone, Debug)]
+#[ferment_macro::export]
+pub struct NodeProbeResult {
+    pub address: String,
+    pub success: bool,
+    /// true if the proofs were verified, false if verification failed and
+    /// None if the node did not return a proof
+    pub proof_verified: Option<bool>,
+    pub response_time_ms: u64,
+    pub protocol_version: Option<u32>,
+    pub block_height: Option<u64>,
+    pub core_chain_locked_height: Option<u32>,
+    pub error_kind: Option<NodeErrorKind>,
+    pub error: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn NodeProbeResult_clone(value: NodeProbeResult) -> NodeProbeResult {
+    value.clone()
+}
+
+async fn probe_node(
+    refresher: EvonodeRefresher,
+    address: String,
+    query: NodeProbeQuery
+) -> NodeProbeResult {
+    let started = Instant::now();
+    let result = check_node(&refresher, &address, query).await;
+
+    match result {
+        Ok((response_time, metadata)) => {
+            refresher.health.record_success(&address, response_time);
+            refresher.set_protocol_version(metadata.protocol_version);
+            let evonodes = refresher.evonode_list.get();
+            if evonodes.contains(&address) {
+                refresher.evonode_list.save(&evonodes);
+            }
+            NodeProbeResult {
+                address,
+                success: true,
+                proof_verified: Some(true),
+                response_time_ms: response_time.as_millis() as u64,
+                protocol_version: Some(metadata.protocol_version),
+                block_height: Some(metadata.height),
+                core_chain_locked_height: Some(metadata.core_chain_locked_height),
+                error_kind: None,
+                error: None,
+            }
+        }
+        Err(e) => {
+            let response_time = started.elapsed();
+            refresher.health.record_failure(&address, &e);
+            let error_kind = NodeErrorKind::from(&e);
+            NodeProbeResult {
+                address,
+                success: false,
+                proof_verified: match error_kind {
+                    NodeErrorKind::ProofVerificationFailed => Some(false),
+                    _ => None
+                },
+                response_time_ms: response_time.as_millis() as u64,
+                protocol_version: None,
+                block_height: None,
+                core_chain_locked_height: None,
+                error_kind: Some(error_kind),
+                error: Some(e.to_string()),
+            }
+        }
+    }
+}
+
+/// Send `query` to each evonode of `addresses` and report how each of them responded
+///
+/// The evonodes of the SDK are probed when `addresses` is empty.  The results are
+/// also recorded in the node health report.
+#[ferment_macro::export]
+pub fn probe_evonodes(
+    rust_sdk: *mut DashSdk,
+    addresses: Vec<String>,
+    query: NodeProbeQuery
+) -> Vec<NodeProbeResult> {
+    let addresses = if addresses.is_empty() {
+        unsafe { (*rust_sdk).get_evonode_refresher().evonode_list.get() }
+    } else {
+        addresses
+    };
+    tracing::info!("probe_evonodes: {} evonodes, {:?}", addresses.len(), query);
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    let refresher = unsafe { (*rust_sdk).get_evonode_refresher() };
+
+    rt.block_on(async {
+        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_PROBES));
+        let mut handles = vec![];
+        for address in addresses {
+            let semaphore = semaphore.clone();
+            let refresher = refresher.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                probe_node(refresher, address, query).await
+            }));
+        }
+
+        let mut results = vec![];
+        for handle in handles {
+            match handle.await {
+                Ok(result) => results.push(result),
+                Err(e) => tracing::warn!("probe_evonodes, join error: {:?}", e)
+            }
+        }
+        results
+    })
+}
+
+#[test]
+fn probe_evonodes_test() {
+    let mut sdk = create_dash_sdk_using_core_testnet();
+    let results = probe_evonodes(&mut sdk, vec![], NodeProbeQuery::DpnsDomains);
+    for result in &results {
+        tracing::info!("{:?}", result);
+    }
+    assert!(results.iter().any(|result| result.success));
+}
@@ -4,8 +4,7 @@ use std::hash::Hash;
 use std::num::NonZeroUsize;
 use std::os::raw::c_void;
 use std::ptr::null;
-use std::sync::Arc;
-use std::thread;
+use std::sync::{Arc, Weak};
 use dapi_grpc::tonic::codegen::Body;
 
 use dpp::data_contract::DataContract;
@@ -18,10 +17,16 @@ use dash_sdk::{Sdk, Error};
 use dpp::identity::IdentityPublicKey;
 use dpp::prelude::CoreBlockHeight;
 use platform_value::types::binary_data::BinaryData;
-use tokio::runtime::{Handle, Runtime};
+use platform_value::string_encoding::Encoding;
+use std::time::Duration;
+use parking_lot::RwLock;
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeFlavor};
 use tokio::sync::mpsc;
 use crate::config::Config;
 
+/// How long [CallbackContextProvider::get_data_contract] waits for a data contract that is not cached
+const DATA_CONTRACT_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
 // not supported
 
 // not supported with ferment
@@ -40,11 +45,9 @@ pub struct CallbackContextProvider {
     pub data_contract_callback: DataContractCallback,
     /// [Sdk] to use when fetching data from Platform
     ///
-    /// Note that if the `sdk` is `None`, the context provider will not be able to fetch data itself and will rely on
+    /// Note that if the `sdk` is not set, the context provider will not be able to fetch data itself and will rely on
     /// values set by the user in the caches: `data_contracts_cache`, `quorum_public_keys_cache`.
-    ///
-    /// We use [Arc] as we have circular dependencies between Sdk and ContextProvider.
-    sdk: Option<Arc<Sdk>>,
+    sdk: SdkReference,
 
     /// Data contracts cache.
     ///
@@ -56,7 +59,7 @@ pub struct CallbackContextProvider {
     /// Key is a tuple of quorum hash and quorum type. Value is a quorum public key.
     ///
     /// Users can insert new quorum public keys into the cache using [`Cache::put`].
-    pub quorum_public_keys_cache: Cache<([u8; 32], u32), [u8; 48]>,
+    pub quorum_public_keys_cache: Arc<Cache<([u8; 32], u32), [u8; 48]>>,
 
     /// Directory where to store dumped data.
     ///
@@ -68,17 +71,33 @@ pub struct CallbackContextProvider {
 impl CallbackContextProvider {
     /// Create new context provider.
     ///
-    /// Note that if the `sdk` is `None`, the context provider will not be able to fetch data itself and will rely on
-    /// values set by the user in the caches: `data_contracts_cache`, `quorum_public_keys_cache`.
-    ///
-    /// Sdk can be set later with [`CallbackContextProvider::set_sdk`].
+    /// The [Sdk] that fetches missing data contracts is set with the [SdkReference]
+    /// returned by [`CallbackContextProvider::sdk_reference`].
     pub fn new(
         context: * const c_void,
         quorum_public_key_callback: u64,
         data_contract_callback: u64,
-        sdk: Option<Arc<Sdk>>,
         data_contract_cache: Arc<Cache<Identifier, DataContract>>,
         quorum_public_keys_cache_size: NonZeroUsize,
+    ) -> Result<Self, Error> {
+        Self::new_with_quorum_public_keys_cache(
+            context,
+            quorum_public_key_callback,
+            data_contract_callback,
+            data_contract_cache,
+            Arc::new(Cache::new(quorum_public_keys_cache_size))
+        )
+    }
+
+    /// Create new context provider that uses `quorum_public_keys_cache`, such as a cache
+    /// that is backed by a [DiskCache](crate::disk_cache::DiskCache) and shared by the
+    /// context providers of a [DashSdk](crate::sdk::DashSdk)
+    pub fn new_with_quorum_public_keys_cache(
+        context: * const c_void,
+        quorum_public_key_callback: u64,
+        data_contract_callback: u64,
+        data_contract_cache: Arc<Cache<Identifier, DataContract>>,
+        quorum_public_keys_cache: Arc<Cache<([u8; 32], u32), [u8; 48]>>,
     ) -> Result<Self, Error> {
         unsafe {
             let callback1: QuorumPublicKeyCallback = std::mem::transmute(quorum_public_key_callback as usize);
@@ -87,22 +106,38 @@ impl CallbackContextProvider {
                 context,
                 quorum_public_key_callback: callback1,
                 data_contract_callback: callback2,
-                sdk,
+                sdk: SdkReference::default(),
                 data_contracts_cache: data_contract_cache,
-                quorum_public_keys_cache: Cache::new(quorum_public_keys_cache_size),
+                quorum_public_keys_cache,
                 #[cfg(feature = "mocks")]
                 dump_dir: None,
             })
         }
     }
 
-    /// Set the Sdk to use when fetching data from Platform.
-    /// This is useful when the Sdk is created after the ContextProvider.
+    /// Reference used to set the Sdk that fetches data from Platform.
     ///
-    /// Note that if the `sdk` is `None`, the context provider will not be able to fetch data itself and will rely on
-    /// values set by the user in the caches: `data_contracts_cache`, `quorum_public_keys_cache`.
-    pub fn set_sdk(&mut self, sdk: Option<Arc<Sdk>>) {
-        self.sdk = sdk;
+    /// The context provider is moved into the Sdk by [Sdk::set_context_provider], so the Sdk
+    /// is set through this reference afterwards.
+    pub fn sdk_reference(&self) -> SdkReference {
+        self.sdk.clone()
+    }
+}
+
+/// Reference from a [CallbackContextProvider] to the [Sdk] that it belongs to
+///
+/// The reference is weak because the [Sdk] owns the context provider.
+#[derive(Clone, Default)]
+pub struct SdkReference(Arc<RwLock<Weak<Sdk>>>);
+
+impl SdkReference {
+    pub fn set(&self, sdk: &Arc<Sdk>) {
+        *self.0.write() = Arc::downgrade(sdk);
+    }
+
+    /// The [Sdk], None if it was not set or was dropped
+    pub fn get(&self) -> Option<Arc<Sdk>> {
+        self.0.read().upgrade()
     }
 }
 
@@ -143,33 +178,25 @@ impl ContextProvider for CallbackContextProvider {
         &self,
         data_contract_id: &Identifier,
     ) -> Result<Option<Arc<DataContract>>, ContextProviderError> {
-        return if let Some(contract) = self.data_contracts_cache.get(data_contract_id) {
-            Ok(Some(contract))
-        } else {
-            Ok(None)
+        if let Some(contract) = self.data_contracts_cache.get(data_contract_id) {
+            return Ok(Some(contract));
+        }
+
+        let sdk = match self.sdk.get() {
+            Some(sdk) => sdk,
+            None => {
+                tracing::warn!("data contract cache miss and no sdk provided, skipping fetch");
+                return Ok(None);
+            }
+        };
+
+        tracing::info!("get_data_contract: fetching {}", data_contract_id.to_string(Encoding::Base58));
+        let data_contract = fetch_data_contract_blocking(&sdk, *data_contract_id)?;
+        if let Some(ref dc) = data_contract {
+            self.data_contracts_cache.put(*data_contract_id, dc.clone());
         };
-        // tracing::info!("CallbackContextProvider: {:p}", &self as *const _);
-
-        // on android, this gets stuck (deadlock)
-        // let sdk = match &self.sdk {
-        //     Some(sdk) => sdk,
-        //     None => {
-        //         tracing::warn!("data contract cache miss and no sdk provided, skipping fetch");
-        //         return Ok(None);
-        //     }
-        // };
-        //
-        // let handle = Handle::current();
-        // let data_contract = tokio::task::block_in_place(|| {
-        //     handle.block_on(async {
-        //         DataContract::fetch(&sdk, *data_contract_id).await
-        //     }).map_err(|e| ContextProviderError::DataContractFailure(e.to_string()))
-        // })?;
-        // if let Some(ref dc) = data_contract {
-        //     self.data_contracts_cache.put(*data_contract_id, dc.clone());
-        // };
-        //
-        // Ok(data_contract.map(Arc::new))
+
+        Ok(data_contract.map(Arc::new))
     }
 
     fn get_platform_activation_height(&self) -> Result<CoreBlockHeight, ContextProviderError> {
@@ -177,36 +204,91 @@ impl ContextProvider for CallbackContextProvider {
     }
 }
 
+/// Fetch a data contract on the runtime of the [Sdk] and wait for the result
+///
+/// [ContextProvider::get_data_contract] is called from within the runtime of the [Sdk] while
+/// a proof is being verified, so the current worker thread is handed over to the runtime with
+/// `block_in_place` while the request runs.  A current thread runtime cannot do that.
+fn fetch_data_contract_blocking(
+    sdk: &Sdk,
+    data_contract_id: Identifier
+) -> Result<Option<DataContract>, ContextProviderError> {
+    let failure = |e: String| ContextProviderError::DataContractFailure(
+        format!("fetching data contract {} failed: {}", data_contract_id.to_string(Encoding::Base58), e)
+    );
+    let handle = Handle::try_current().map_err(|e| failure(e.to_string()))?;
+    if handle.runtime_flavor() != RuntimeFlavor::MultiThread {
+        return Err(failure("the runtime is not multi-threaded".to_string()));
+    }
+    let result = tokio::task::block_in_place(|| {
+        handle.block_on(tokio::time::timeout(DATA_CONTRACT_FETCH_TIMEOUT, DataContract::fetch(sdk, data_contract_id)))
+    });
+    match result {
+        Ok(result) => result.map_err(|e| failure(e.to_string())),
+        Err(_) => Err(failure(format!("no response in {:?}", DATA_CONTRACT_FETCH_TIMEOUT)))
+    }
+}
+
 unsafe impl Send for CallbackContextProvider {}
 unsafe impl Sync for CallbackContextProvider {}
 
 
+/// Persistent storage behind a [Cache], such as [DiskCache](crate::disk_cache::DiskCache)
+pub trait CacheStore<K, V>: Send + Sync {
+    /// Load the value stored under `k`, None if there is none
+    fn load(&self, k: &K) -> Option<V>;
+    /// Store `v` under `k`, failures are logged and ignored
+    fn store(&self, k: &K, v: &V);
+}
+
 /// Thread-safe cache of various objects inside the SDK.
 ///
 /// This is used to cache objects that are expensive to fetch from the platform, like data contracts.
+/// Values that are not in memory are loaded from the [CacheStore] if there is one.
 pub struct Cache<K: Hash + Eq, V> {
     // We use a Mutex to allow access to the cache when we don't have mutable &self
     // And we use Arc to allow multiple threads to access the cache without having to clone it
     inner: std::sync::RwLock<lru::LruCache<K, Arc<V>>>,
+    store: Option<Arc<dyn CacheStore<K, V>>>,
 }
 
-impl<K: Hash + Eq, V> Cache<K, V> {
+impl<K: Hash + Eq + Clone, V> Cache<K, V> {
     /// Create new cache
     pub fn new(capacity: NonZeroUsize) -> Self {
         Self {
             // inner: std::sync::Mutex::new(lru::LruCache::new(capacity)),
             inner: std::sync::RwLock::new(lru::LruCache::new(capacity)),
+            store: None,
+        }
+    }
+
+    /// Create new cache that keeps a copy of its values in `store`
+    pub fn with_store(capacity: NonZeroUsize, store: Arc<dyn CacheStore<K, V>>) -> Self {
+        Self {
+            inner: std::sync::RwLock::new(lru::LruCache::new(capacity)),
+            store: Some(store),
         }
     }
 
     /// Get a reference to the value stored under `k`.
     pub fn get(&self, k: &K) -> Option<Arc<V>> {
+        {
+            let mut guard = self.inner.write().expect("cache lock poisoned");
+            if let Some(v) = guard.get(k) {
+                return Some(Arc::clone(v));
+            }
+        }
+        let v = Arc::new(self.store.as_ref()?.load(k)?);
         let mut guard = self.inner.write().expect("cache lock poisoned");
-        guard.get(k).map(Arc::clone)
+        guard.put(k.clone(), Arc::clone(&v));
+        Some(v)
     }
 
     /// Insert a new value into the cache.
     pub fn put(&self, k: K, v: V) {
+        if let Some(store) = &self.store {
+            store.store(&k, &v);
+        }
         let mut guard = self.inner.write().expect("cache lock poisoned");
         guard.put(k, Arc::new(v));
     }
@@ -235,3 +317,66 @@ impl<K: Hash + Eq, V> Cache<K, V> {
 //         }
 //     }
 // }
+
+#[test]
+fn get_data_contract_cache_miss_test() {
+    use dash_sdk::SdkBuilder;
+    use dpp::system_data_contracts::load_system_data_contract;
+    use data_contracts::SystemDataContract;
+    use platform_version::version::PlatformVersion;
+    use rs_dapi_client::RequestSettings;
+    use crate::config::PlatformNetwork;
+
+    extern "C" fn no_quorum_public_key(_context: * const c_void, _quorum_type: u32, _quorum_hash: *const u8, _core_chain_locked_height: u32, _result: * mut u8) {}
+    #[allow(improper_ctypes_definitions)]
+    extern "C" fn no_data_contract(_id: &Identifier) -> DataContract {
+        unreachable!("the data contract callback is not used")
+    }
+
+    let data_contract_cache = Arc::new(Cache::new(NonZeroUsize::new(10).unwrap()));
+    let provider = CallbackContextProvider::new(
+        null(),
+        no_quorum_public_key as u64,
+        no_data_contract as u64,
+        data_contract_cache.clone(),
+        NonZeroUsize::new(10).unwrap()
+    ).unwrap();
+    let id = Identifier::from(dpns_contract::ID_BYTES);
+
+    // without an sdk nothing is fetched
+    assert!(provider.get_data_contract(&id).unwrap().is_none());
+
+    // nothing listens on this port, so the fetch fails right away
+    let config = Config::new_for_network(PlatformNetwork::Regtest);
+    let sdk = Arc::new(
+        SdkBuilder::new(config.new_address_list(vec!["127.0.0.1:1".to_string()]))
+            .with_settings(RequestSettings { retries: Some(0), ..Default::default() })
+            .build()
+            .unwrap()
+    );
+    provider.sdk_reference().set(&sdk);
+    let provider = Arc::new(provider);
+
+    // proofs are verified on the workers of the runtime of the sdk
+    let rt = Builder::new_multi_thread().enable_all().build().unwrap();
+    let task_provider = provider.clone();
+    let result = rt.block_on(async move {
+        tokio::spawn(async move { task_provider.get_data_contract(&id) }).await.unwrap()
+    });
+    assert!(matches!(result, Err(ContextProviderError::DataContractFailure(_))));
+    assert!(data_contract_cache.get(&id).is_none());
+
+    // a current thread runtime cannot be blocked in place
+    let rt = Builder::new_current_thread().enable_all().build().unwrap();
+    let result = rt.block_on(async { provider.get_data_contract(&id) });
+    assert!(matches!(result, Err(ContextProviderError::DataContractFailure(_))));
+
+    // cached data contracts are returned without a request
+    let dpns = load_system_data_contract(SystemDataContract::DPNS, PlatformVersion::latest()).unwrap();
+    data_contract_cache.put(id, dpns);
+    assert!(provider.get_data_contract(&id).unwrap().is_some());
+
+    // the sdk is not kept alive by its context provider
+    drop(sdk);
+    assert!(provider.sdk_reference().get().is_none());
+}
@@ -1,5 +1,7 @@
 use std::collections::{BTreeMap, HashMap};
 use std::convert::identity;
+use std::fmt::Debug;
+use std::future::Future;
 use std::io;
 use std::io::{Cursor, Write};
 use std::num::NonZeroUsize;
@@ -42,7 +44,6 @@ use dpp::prelude::{BlockHeight, CoreBlockHeight, UserFeeIncrease};
 use dpp::ProtocolError;
 use dpp::util::entropy_generator::{DefaultEntropyGenerator, EntropyGenerator};
 use platform_value::{Identifier, IdentifierBytes32, Value};
-use platform_value::string_encoding::Encoding;
 use platform_value::types::binary_data::BinaryData;
 use platform_version::version::PlatformVersion;
 use simple_signer::signer::SimpleSigner;
@@ -50,7 +51,7 @@ use serde::{Deserialize, Serialize};
 use tokio::runtime::Builder;
 use tracing::trace;
 use rand::random;
-use crate::config::{Config, EntryPoint};
+use crate::config::{EntryPoint, PlatformNetwork};
 use crate::logs::setup_logs;
 use crate::provider::Cache;
 use dapi_grpc::platform::v0::{StateTransitionBroadcastError, WaitForStateTransitionResultResponse};
@@ -64,6 +65,23 @@ use dpp::serialization::PlatformSerializable;
 use drive_proof_verifier::types::Documents;
 use rs_dapi_client::transport::BoxFuture;
 use dash_sdk::platform::transition::replace_document::ReplaceDocument;
+use crate::error::PlatformError;
+use crate::request::{RequestResult, SdkPtr, spawn_request};
+use crate::health::RequestRecorder;
+use dash_sdk::platform::block_info_from_metadata::block_info_from_metadata;
+use dash_sdk::platform::transition::broadcast_request::BroadcastRequestForStateTransition;
+use dapi_grpc::platform::VersionedGrpcResponse;
+use dpp::state_transition::documents_batch_transition::DocumentsBatchTransition;
+use dpp::state_transition::documents_batch_transition::methods::v0::DocumentsBatchTransitionMethodsV0;
+use dpp::state_transition::proof_result::StateTransitionProofResult;
+use drive::drive::Drive;
+use drive_proof_verifier::error::ContextProviderError;
+use rs_dapi_client::DapiRequest;
+use dpp::identity::accessors::{IdentityGettersV0, IdentitySettersV0};
+use dpp::state_transition::identity_update_transition::IdentityUpdateTransition;
+use dpp::state_transition::identity_update_transition::methods::IdentityUpdateTransitionMethodsV0;
+use dpp::state_transition::public_key_in_creation::IdentityPublicKeyInCreation;
+
 pub fn get_wait_result_error(response: &WaitForStateTransitionResultResponse) -> Option<&StateTransitionBroadcastError> {
     match &response.version {
         Some(dapi_grpc::platform::v0::wait_for_state_transition_result_response::Version::V0(response_v0)) => {
@@ -77,118 +95,187 @@ pub fn get_wait_result_error(response: &WaitForStateTransitionResultResponse) ->
     None
 }
 
-pub async fn wait_for_response_concurrent(
-    new_preorder_document: &Document,
-    sdk: &Sdk,
-    preorder_transition: StateTransition,
-    data_contract: Arc<DataContract>,
-    settings: PutSettings
-) -> Result<Document, dash_sdk::Error> {
-    let mut handles = vec![];
+/// How many evonodes are asked for the result of a state transition and how many of
+/// them must return a verified result before it is accepted
+#[derive(Clone, Copy, Debug)]
+pub struct WaitSettings {
+    pub requests: usize,
+    pub confirmations: usize,
+}
 
-    for i in 0..5 {
-        let new_preorder_document = new_preorder_document.clone();
-        let sdk = sdk.clone();
-        let preorder_transition = preorder_transition.clone();
-        let data_contract = data_contract.clone();
-        let settings = Some(settings.clone());
+impl Default for WaitSettings {
+    fn default() -> Self {
+        WaitSettings {
+            requests: 5,
+            confirmations: 3,
+        }
+    }
+}
 
-        tracing::info!("spawning thread {} of 5", i + 1);
-        let handle = tokio::spawn(async move {
-            <dpp::document::Document as PutDocument<SimpleSigner>>::wait_for_response::<'_, '_, '_>(
-                &new_preorder_document,
-                &sdk,
-                preorder_transition,
-                data_contract,
-                settings
-            ).await
-        });
+// Start `wait_settings.requests` waits for the same result concurrently and return the
+// result once `wait_settings.confirmations` of them have succeeded
+async fn wait_concurrent<T, E, F, W>(
+    name: &str,
+    wait_settings: WaitSettings,
+    wait: W
+) -> Result<T, E>
+where
+    T: Debug + Send + 'static,
+    E: Debug + From<Error> + Send + 'static,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    W: Fn() -> F
+{
+    let mut handles = vec![];
 
-        handles.push(handle);
+    for i in 0..wait_settings.requests {
+        tracing::info!("{}: spawning thread {} of {}", name, i + 1, wait_settings.requests);
+        handles.push(tokio::spawn(wait()));
     }
 
     let mut success_count = 0;
-    let mut last_error: Option<Error> = None;
+    let mut last_error: Option<E> = None;
 
     for handle in handles {
         match handle.await {
-            Ok(Ok(document)) => {
+            Ok(Ok(result)) => {
                 success_count += 1;
-                if success_count >= 3 {
-                    tracing::warn!("wait_for_response_concurrent, success: {:?}", document);
-                    return Ok(document);
+                if success_count >= wait_settings.confirmations {
+                    tracing::warn!("{}, success: {:?}", name, result);
+                    return Ok(result);
                 }
             }
             Ok(Err(e)) => {
-                tracing::warn!("wait_for_response_concurrent, response error: {:?}", e);
-                last_error = Some(Error::from(e));
+                tracing::warn!("{}, response error: {:?}", name, e);
+                last_error = Some(e);
             }
             Err(e) => {
-                tracing::warn!("wait_for_response_concurrent, join error: {:?}", e);
-                last_error = Some(Error::Generic(e.to_string()));
+                tracing::warn!("{}, join error: {:?}", name, e);
+                last_error = Some(Error::Generic(e.to_string()).into());
             }
         }
     }
-    tracing::warn!("wait_for_response_concurrent, all requests failed");
+    tracing::warn!("{}, all requests failed", name);
+
+    Err(last_error.unwrap_or(Error::Generic("All requests failed".to_string()).into()))
+}
 
-    Err(last_error.unwrap_or(Error::Generic("All requests failed".to_string())))
+pub async fn wait_for_response_concurrent(
+    new_preorder_document: &Document,
+    sdk: &Sdk,
+    preorder_transition: StateTransition,
+    data_contract: Arc<DataContract>,
+    settings: PutSettings,
+    wait_settings: WaitSettings,
+    recorder: &RequestRecorder
+) -> Result<Document, dash_sdk::Error> {
+    wait_concurrent("wait_for_response_concurrent", wait_settings, || {
+        let new_preorder_document = new_preorder_document.clone();
+        let sdk = sdk.clone();
+        let preorder_transition = preorder_transition.clone();
+        let data_contract = data_contract.clone();
+        let settings = Some(settings.clone());
+        let recorder = recorder.clone();
+        async move {
+            let result = <dpp::document::Document as PutDocument<SimpleSigner>>::wait_for_response::<'_, '_, '_>(
+                &new_preorder_document,
+                &sdk,
+                preorder_transition,
+                data_contract,
+                settings
+            ).await;
+            recorder.record_result(&result);
+            result
+        }
+    }).await
 }
 
 pub async fn wait_for_response_concurrent_identity(
     identity: &Identity,
     sdk: &Sdk,
     state_transition: &StateTransition,
+    wait_settings: WaitSettings,
+    recorder: &RequestRecorder
 ) -> Result<Identity, dash_sdk::Error> {
-    let mut handles = vec![];
-
-    for i in 0..5 {
+    wait_concurrent("wait_for_response_concurrent_identity", wait_settings, || {
         let sdk = sdk.clone();
-        //let settings = Some(settings.clone());
         let identity = identity.clone();
         let state_transition = state_transition.clone();
-        tracing::info!("spawning thread {} of 5", i + 1);
-        let handle = tokio::spawn(async move {
-            <Identity as PutIdentity<SimpleSigner>>::wait_for_response::<'_, '_, '_, '_>(
+        let recorder = recorder.clone();
+        async move {
+            let result = <Identity as PutIdentity<SimpleSigner>>::wait_for_response::<'_, '_, '_, '_>(
                 &identity,
                 &sdk,
                 &state_transition
-            ).await
-        });
-
-        handles.push(handle);
-    }
+            ).await;
+            recorder.record_result(&result);
+            result
+        }
+    }).await
+}
 
-    let mut success_count = 0;
-    let mut last_error: Option<Error> = None;
+fn verify_state_transition_result(
+    sdk: &Sdk,
+    state_transition: &StateTransition,
+    response: WaitForStateTransitionResultResponse
+) -> Result<StateTransitionProofResult, Error> {
+    let block_info = block_info_from_metadata(response.metadata()?)?;
+    let proof = response.proof_owned()?;
+    let context_provider = sdk.context_provider()
+        .ok_or(Error::from(ContextProviderError::Config("Context provider not initialized".to_string())))?;
+
+    let (_, result) = Drive::verify_state_transition_was_executed_with_proof(
+        state_transition,
+        &block_info,
+        proof.grovedb_proof.as_slice(),
+        &context_provider.as_contract_lookup_fn(),
+        sdk.version(),
+    )?;
+    Ok(result)
+}
 
-    for handle in handles {
-        match handle.await {
-            Ok(Ok(identity)) => {
-                success_count += 1;
-                if success_count >= 3 {
-                    tracing::warn!("wait_for_response_concurrent, success: {:?}", identity);
-                    return Ok(identity);
-                }
-            }
-            Ok(Err(e)) => {
-                tracing::warn!("wait_for_response_concurrent, response error: {:?}", e);
-                last_error = Some(Error::from(e));
-            }
-            Err(e) => {
-                tracing::warn!("wait_for_response_concurrent, join error: {:?}", e);
-                last_error = Some(Error::Generic(e.to_string()));
-            }
-        }
+/// Wait for the result of a state transition that does not have a
+/// `wait_for_response` method in the SDK and verify the proof
+pub async fn wait_for_state_transition_result(
+    sdk: &Sdk,
+    state_transition: &StateTransition,
+    request_settings: RequestSettings,
+    recorder: &RequestRecorder
+) -> Result<StateTransitionProofResult, PlatformError> {
+    let request = state_transition.wait_for_state_transition_result_request()?;
+    let response = request.execute(sdk, request_settings).await
+        .map_err(|e| {
+            recorder.record_dapi_error(&e);
+            PlatformError::from(e)
+        })?;
+
+    if let Some(error) = get_wait_result_error(&response) {
+        tracing::warn!("wait_for_state_transition_result, error: {:?}", error);
+        return Err(error.into());
     }
-    tracing::warn!("wait_for_response_concurrent, all requests failed");
+    Ok(verify_state_transition_result(sdk, state_transition, response)?)
+}
 
-    Err(last_error.unwrap_or(Error::Generic("All requests failed".to_string())))
+pub async fn wait_for_state_transition_result_concurrent(
+    sdk: &Sdk,
+    state_transition: &StateTransition,
+    request_settings: RequestSettings,
+    wait_settings: WaitSettings,
+    recorder: &RequestRecorder
+) -> Result<StateTransitionProofResult, PlatformError> {
+    wait_concurrent("wait_for_state_transition_result_concurrent", wait_settings, || {
+        let sdk = sdk.clone();
+        let state_transition = state_transition.clone();
+        let recorder = recorder.clone();
+        async move {
+            wait_for_state_transition_result(&sdk, &state_transition, request_settings, &recorder).await
+        }
+    }).await
 }
 
 //#[ferment_macro::export]
 pub type SignerCallback = extern "C" fn(context: usize, key_data: * const u8, key_len: u32, data: * const u8, data_len: u32, result: * mut u8) -> u32;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CallbackSigner {
     signer_callback: SignerCallback,
     signer_context: usize
@@ -199,6 +286,9 @@ impl CallbackSigner {
         signer_context: usize,
         signer_callback: u64,
     ) -> Result<Self, Error> {
+        if signer_callback == 0 {
+            return Err(Error::Config("the signer callback is not set".to_string()));
+        }
         unsafe {
             let callback: SignerCallback = std::mem::transmute(signer_callback as usize);
             Ok(Self {
@@ -347,6 +437,10 @@ impl From<AssetLockProofFFI> for AssetLockProof {
     }
 }
 
+/// Register `identity` on platform using the asset lock and wait for the result
+///
+/// The asset lock private key is for mainnet or testnet, depending on `is_testnet`.
+/// Use [put_identity_with_sdk_network] for other networks.
 #[ferment_macro::export]
 pub fn put_identity_sdk(
     rust_sdk: *mut DashSdk,
@@ -356,70 +450,181 @@ pub fn put_identity_sdk(
     signer_context: usize,
     signer_callback: u64,
     is_testnet: bool
-) -> Result<Identity, String> {
+) -> Result<Identity, PlatformError> {
+    let network = PlatformNetwork::from_is_testnet(is_testnet).core_network();
+    put_identity_on_network(rust_sdk, identity, asset_lock_proof, asset_lock_proof_private_key, signer_context, signer_callback, network)
+}
+
+/// Register `identity` on the network of the SDK using the asset lock and wait for the result
+#[ferment_macro::export]
+pub fn put_identity_with_sdk_network(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<Identity, PlatformError> {
+    let network = unsafe { (*rust_sdk).get_network() }.core_network();
+    put_identity_on_network(rust_sdk, identity, asset_lock_proof, asset_lock_proof_private_key, signer_context, signer_callback, network)
+}
+
+fn put_identity_on_network(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    signer_context: usize,
+    signer_callback: u64,
+    network: Network
+) -> Result<Identity, PlatformError> {
     let rt = unsafe { (*rust_sdk).get_runtime() };
 
     // Execute the async block using the Tokio runtime
     rt.block_on(async {
-        // Your async code here
-        let cfg = Config::new();
-        trace!("Setting up SDK");
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
-        trace!("Finished SDK, {:?}", sdk);
-        trace!("Set up network, private key and signer");
-
-        let network = if is_testnet {
-            Network::Testnet
-        } else {
-            Network::Dash
-        };
-        let private_key = match PrivateKey::from_slice(asset_lock_proof_private_key.as_slice(), network) {
-            Ok(pk) => pk,
-            Err(e) => return Err(e.to_string())
-        };
         let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
-        let request_settings = unsafe { (*rust_sdk).get_request_settings() };
-        tracing::info!("Call Identity::put_to_platform_and_wait_for_response");
-
-        let asset_lock_proof: AssetLockProof = asset_lock_proof.into();
-        // this PR has not been merged yet, but there is a way to detect if the put_identity will fail
-        // match asset_lock_proof.verify(&sdk) {
-        //     Ok(_) => {},
-        //     Err(error) => {
-        //         return Err(error.to_string())
-        //     }
-        // }
-
-        let state_transition_result = Identity::put_to_platform(
-            &identity,
-            &sdk,
-            asset_lock_proof.into(),
-            &private_key,
-            &signer,
-            request_settings
-        ).await;
-
-        let state_transition = match state_transition_result {
-            Ok(st) => st,
-            Err(err) => return Err(err.to_string())
-        };
-
-        tracing::info!("state transition (signable): {}", hex::encode(state_transition.signable_bytes().unwrap()));
-        tracing::info!("state transition (serialized): {}", hex::encode(state_transition.serialize_to_bytes().unwrap()));
-
-        let identity_result = wait_for_response_concurrent_identity(
-            &identity,
-            &sdk,
-            &state_transition
-        ).await;
+        put_identity_to_platform(
+            SdkPtr(rust_sdk),
+            identity,
+            asset_lock_proof,
+            asset_lock_proof_private_key,
+            signer,
+            network
+        ).await
+    })
+}
 
-        return match identity_result {
-            Ok(identity) => Ok(identity),
-            Err(e) => Err(e.to_string())
-        }
+/// Non-blocking version of [put_identity_sdk]
+///
+/// Returns the request id, the result is [RequestResult::Identity].
+#[ferment_macro::export]
+pub fn put_identity_sdk_async(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    signer_context: usize,
+    signer_callback: u64,
+    is_testnet: bool,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let network = PlatformNetwork::from_is_testnet(is_testnet).core_network();
+    spawn_put_identity(rust_sdk, identity, asset_lock_proof, asset_lock_proof_private_key, signer_context, signer_callback, network, completion_context, completion_callback)
+}
+
+/// Non-blocking version of [put_identity_with_sdk_network]
+///
+/// Returns the request id, the result is [RequestResult::Identity].
+#[ferment_macro::export]
+pub fn put_identity_with_sdk_network_async(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    signer_context: usize,
+    signer_callback: u64,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let network = unsafe { (*rust_sdk).get_network() }.core_network();
+    spawn_put_identity(rust_sdk, identity, asset_lock_proof, asset_lock_proof_private_key, signer_context, signer_callback, network, completion_context, completion_callback)
+}
+
+fn spawn_put_identity(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    signer_context: usize,
+    signer_callback: u64,
+    network: Network,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        put_identity_to_platform(
+            rust_sdk,
+            identity,
+            asset_lock_proof,
+            asset_lock_proof_private_key,
+            signer,
+            network
+        ).await.map(RequestResult::Identity)
     })
 }
 
+/// Register `identity` on platform using the asset lock and wait for the result
+///
+/// `network` is the network of the asset lock private key.
+pub async fn put_identity_to_platform(
+    rust_sdk: SdkPtr,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    signer: CallbackSigner,
+    network: Network
+) -> Result<Identity, PlatformError> {
+    trace!("Setting up SDK");
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    trace!("Finished SDK, {:?}", sdk);
+    trace!("Set up network, private key and signer");
+
+    let private_key = match PrivateKey::from_slice(asset_lock_proof_private_key.as_slice(), network) {
+        Ok(pk) => pk,
+        Err(e) => return Err(PlatformError::InvalidInput(e.to_string()))
+    };
+    let request_settings = unsafe { (*rust_sdk.0).get_request_settings() };
+    tracing::info!("Call Identity::put_to_platform_and_wait_for_response");
+
+    let asset_lock_proof: AssetLockProof = asset_lock_proof.into();
+    // this PR has not been merged yet, but there is a way to detect if the put_identity will fail
+    // match asset_lock_proof.verify(&sdk) {
+    //     Ok(_) => {},
+    //     Err(error) => {
+    //         return Err(error.to_string())
+    //     }
+    // }
+
+    let recorder = unsafe { (*rust_sdk.0).get_request_recorder() };
+    let state_transition_result = Identity::put_to_platform(
+        &identity,
+        &sdk,
+        asset_lock_proof.into(),
+        &private_key,
+        &signer,
+        request_settings
+    ).await;
+    recorder.record_result(&state_transition_result);
+
+    let state_transition = match state_transition_result {
+        Ok(st) => st,
+        Err(err) => return Err(err.into())
+    };
+
+    tracing::info!("state transition (signable): {}", hex::encode(state_transition.signable_bytes().unwrap()));
+    tracing::info!("state transition (serialized): {}", hex::encode(state_transition.serialize_to_bytes().unwrap()));
+
+    let identity_result = wait_for_response_concurrent_identity(
+        &identity,
+        &sdk,
+        &state_transition,
+        unsafe { (*rust_sdk.0).wait_settings },
+        &recorder
+    ).await;
+
+    return match identity_result {
+        Ok(identity) => Ok(identity),
+        Err(e) => Err(e.into())
+    }
+}
+
+/// Top up `identity` using the asset lock, returns the new balance
+///
+/// The asset lock private key is for mainnet or testnet, depending on `is_testnet`.
+/// Use [topup_identity_with_sdk_network] for other networks.
 #[ferment_macro::export]
 pub fn topup_identity_sdk(
     rust_sdk: *mut DashSdk,
@@ -427,47 +632,264 @@ pub fn topup_identity_sdk(
     asset_lock_proof: AssetLockProofFFI,
     asset_lock_proof_private_key: Vec<u8>,
     is_testnet: bool
-) -> Result<u64, String> {
+) -> Result<u64, PlatformError> {
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    let network = PlatformNetwork::from_is_testnet(is_testnet).core_network();
+
+    // Execute the async block using the Tokio runtime
+    rt.block_on(topup_identity_on_platform(
+        SdkPtr(rust_sdk),
+        identity,
+        asset_lock_proof,
+        asset_lock_proof_private_key,
+        network
+    ))
+}
+
+/// Top up `identity` on the network of the SDK using the asset lock, returns the new balance
+#[ferment_macro::export]
+pub fn topup_identity_with_sdk_network(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>
+) -> Result<u64, PlatformError> {
     let rt = unsafe { (*rust_sdk).get_runtime() };
+    let network = unsafe { (*rust_sdk).get_network() }.core_network();
 
     // Execute the async block using the Tokio runtime
+    rt.block_on(topup_identity_on_platform(
+        SdkPtr(rust_sdk),
+        identity,
+        asset_lock_proof,
+        asset_lock_proof_private_key,
+        network
+    ))
+}
+
+/// Non-blocking version of [topup_identity_sdk]
+///
+/// Returns the request id, the result is [RequestResult::Balance].
+#[ferment_macro::export]
+pub fn topup_identity_sdk_async(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    is_testnet: bool,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let network = PlatformNetwork::from_is_testnet(is_testnet).core_network();
+    spawn_topup_identity(rust_sdk, identity, asset_lock_proof, asset_lock_proof_private_key, network, completion_context, completion_callback)
+}
+
+/// Non-blocking version of [topup_identity_with_sdk_network]
+///
+/// Returns the request id, the result is [RequestResult::Balance].
+#[ferment_macro::export]
+pub fn topup_identity_with_sdk_network_async(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let network = unsafe { (*rust_sdk).get_network() }.core_network();
+    spawn_topup_identity(rust_sdk, identity, asset_lock_proof, asset_lock_proof_private_key, network, completion_context, completion_callback)
+}
+
+fn spawn_topup_identity(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    network: Network,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        topup_identity_on_platform(
+            rust_sdk,
+            identity,
+            asset_lock_proof,
+            asset_lock_proof_private_key,
+            network
+        ).await.map(RequestResult::Balance)
+    })
+}
+
+/// Top up `identity` using the asset lock, returns the new balance
+///
+/// `network` is the network of the asset lock private key.
+pub async fn topup_identity_on_platform(
+    rust_sdk: SdkPtr,
+    identity: Identity,
+    asset_lock_proof: AssetLockProofFFI,
+    asset_lock_proof_private_key: Vec<u8>,
+    network: Network
+) -> Result<u64, PlatformError> {
+    trace!("Setting up SDK");
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    trace!("Finished SDK, {:?}", sdk);
+    trace!("Set up network, private key and signer");
+
+    let private_key = match PrivateKey::from_slice(asset_lock_proof_private_key.as_slice(), network) {
+        Ok(pk) => pk,
+        Err(e) => return Err(PlatformError::InvalidInput(e.to_string()))
+    };
+
+    let user_fee_increase = 1;
+    let request_settings = unsafe { (*rust_sdk.0).get_request_settings() };
+
+    trace!("Call Identity::top_up_identity");
+    let identity_result = identity.top_up_identity(
+        &sdk,
+        asset_lock_proof.into(),
+        &private_key,
+        Some(user_fee_increase),
+        request_settings
+    ).await;
+
+    match identity_result {
+        Ok(identity) => Ok(identity),
+        Err(err) => Err(err.into())
+    }
+}
+
+/// Add public keys to an identity and disable existing keys
+///
+/// The transition is signed with `master_key`.  Each key in `add_keys` also signs the transition
+/// as proof of possession, so `signer_callback` must be able to sign with the new keys.
+/// Returns the updated identity.
+#[ferment_macro::export]
+pub fn update_identity_keys_sdk(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    add_keys: Vec<IdentityPublicKey>,
+    disable_key_ids: Vec<KeyID>,
+    master_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<Identity, PlatformError> {
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+
     rt.block_on(async {
-        // Your async code here
-        let cfg = Config::new();
-        trace!("Setting up SDK");
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
-        trace!("Finished SDK, {:?}", sdk);
-        trace!("Set up network, private key and signer");
-
-        let network = if is_testnet {
-            Network::Testnet
-        } else {
-            Network::Dash
-        };
-        let private_key = match PrivateKey::from_slice(asset_lock_proof_private_key.as_slice(), network) {
-            Ok(pk) => pk,
-            Err(e) => return Err(e.to_string())
-        };
-
-        let user_fee_increase = 1;
-        let request_settings = unsafe { (*rust_sdk).get_request_settings() };
-
-        trace!("Call Identity::top_up_identity");
-        let identity_result = identity.top_up_identity(
-            &sdk,
-            asset_lock_proof.into(),
-            &private_key,
-            Some(user_fee_increase),
-            request_settings
-        ).await;
-
-        match identity_result {
-            Ok(identity) => Ok(identity),
-            Err(err) => Err(err.to_string())
-        }
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        update_identity_keys_on_platform(
+            SdkPtr(rust_sdk),
+            identity,
+            add_keys,
+            disable_key_ids,
+            master_key,
+            signer
+        ).await
+    })
+}
+
+/// Non-blocking version of [update_identity_keys_sdk]
+///
+/// Returns the request id, the result is [RequestResult::Identity].
+#[ferment_macro::export]
+pub fn update_identity_keys_sdk_async(
+    rust_sdk: *mut DashSdk,
+    identity: Identity,
+    add_keys: Vec<IdentityPublicKey>,
+    disable_key_ids: Vec<KeyID>,
+    master_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        update_identity_keys_on_platform(
+            rust_sdk,
+            identity,
+            add_keys,
+            disable_key_ids,
+            master_key,
+            signer
+        ).await.map(RequestResult::Identity)
     })
 }
 
+/// Add and disable the keys of `identity` and wait for the result
+pub async fn update_identity_keys_on_platform(
+    rust_sdk: SdkPtr,
+    identity: Identity,
+    add_keys: Vec<IdentityPublicKey>,
+    disable_key_ids: Vec<KeyID>,
+    master_key: IdentityPublicKey,
+    signer: CallbackSigner
+) -> Result<Identity, PlatformError> {
+    if add_keys.is_empty() && disable_key_ids.is_empty() {
+        return Err(PlatformError::InvalidInput("no keys to add or disable".to_string()));
+    }
+    if master_key.security_level() != SecurityLevel::MASTER {
+        return Err(PlatformError::InvalidInput(format!("key {} is not a master key", master_key.id())));
+    }
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    let request_settings = unsafe { (*rust_sdk.0).get_request_settings() };
+    let settings = PutSettings {
+        request_settings,
+        identity_nonce_stale_time_s: None,
+        user_fee_increase: None,
+    };
+
+    // the revision of the transition must be one more than the current revision
+    let mut current_identity = match Identity::fetch_with_settings(&sdk, identity.id(), request_settings).await {
+        Ok(Some(identity)) => identity,
+        Ok(None) => return Err(PlatformError::NotFound(format!("identity {} not found", identity.id()))),
+        Err(e) => return Err(e.into())
+    };
+    current_identity.set_revision(current_identity.revision() + 1);
+
+    let identity_nonce = sdk.get_identity_nonce(current_identity.id(), true, Some(settings)).await?;
+    trace!("update_identity_keys_sdk: revision {}, nonce {}", current_identity.revision(), identity_nonce);
+
+    let add_public_keys: Vec<IdentityPublicKeyInCreation> = add_keys.iter()
+        .map(|key| key.into())
+        .collect();
+
+    let transition = IdentityUpdateTransition::try_from_identity_with_signer(
+        &current_identity,
+        &master_key.id(),
+        add_public_keys,
+        disable_key_ids,
+        identity_nonce,
+        settings.user_fee_increase.unwrap_or_default(),
+        &signer,
+        sdk.version(),
+        None
+    )?;
+
+    let recorder = unsafe { (*rust_sdk.0).get_request_recorder() };
+    let request = transition.broadcast_request_for_state_transition()?;
+    request.clone().execute(&sdk, settings.request_settings).await
+        .map_err(|e| {
+            recorder.record_dapi_error(&e);
+            PlatformError::from(e)
+        })?;
+
+    match wait_for_state_transition_result_concurrent(&sdk, &transition, request_settings, unsafe { (*rust_sdk.0).wait_settings }, &recorder).await? {
+        StateTransitionProofResult::VerifiedPartialIdentity(_) => {
+            match Identity::fetch_with_settings(&sdk, current_identity.id(), request_settings).await {
+                Ok(Some(identity)) => Ok(identity),
+                Ok(None) => Err(PlatformError::NotFound(format!("identity {} not found", current_identity.id()))),
+                Err(e) => Err(e.into())
+            }
+        }
+        result => Err(PlatformError::ProofVerificationFailed(
+            format!("unexpected proof result: {:?}", result)
+        ))
+    }
+}
+
 fn put_document_with_retry(
     sdk: Arc<Sdk>,
     data_contract_cache: Arc<Cache<Identifier, DataContract>>,
@@ -477,8 +899,9 @@ fn put_document_with_retry(
     identity_public_key: IdentityPublicKey,
     signer_callback: CallbackSigner,
     put_settings: PutSettings,
+    recorder: RequestRecorder,
     retries_left: usize,
-) -> BoxFuture<'static, Result<StateTransition, Error>> {
+) -> BoxFuture<'static, Result<StateTransition, PlatformError>> {
     Box::pin(async move {
         match new_document.put_to_platform(
             &sdk,
@@ -490,8 +913,10 @@ fn put_document_with_retry(
         ).await {
             Ok(documents) => Ok(documents),
             Err(error) => {
+                recorder.record_error(&error);
+                let error = PlatformError::from(error);
                 if retries_left > 1 {
-                    if error.to_string().contains("contract not found error") {
+                    if let PlatformError::DataContractNotFound(_) = error {
                         if (data_contract_cache.get(&document_type.data_contract_id()) != None) {
                             return put_document_with_retry(
                                 sdk,
@@ -502,6 +927,7 @@ fn put_document_with_retry(
                                 identity_public_key,
                                 signer_callback,
                                 put_settings,
+                                recorder,
                                 retries_left - 1
                             ).await;
                         }
@@ -524,94 +950,137 @@ pub fn put_document_sdk(
     core_block_height: CoreBlockHeight,
     signer_context: usize,
     signer_callback: u64
-) -> Result<Document, String> {
+) -> Result<Document, PlatformError> {
 
     let rt = unsafe { (*rust_sdk).get_runtime() };
     rt.block_on(async {
-        // Your async code here
-        let cfg = Config::new();
-        trace!("Setting up SDK");
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
-
-        trace!("Finished SDK, {:?}", sdk);
-        trace!("Set up entropy, data contract and signer");
-
-        let data_contract = match unsafe { (*rust_sdk).get_data_contract(&data_contract_id) } {
-            Some(data_contract) => data_contract.clone(),
-            None => {
-                let request_settings = unsafe { (*rust_sdk).get_request_settings() };
-                match (DataContract::fetch_with_settings(&sdk, data_contract_id.clone(), request_settings)
-                    .await) {
-                    Ok(Some(data_contract)) => {
-                        unsafe { (*rust_sdk).add_data_contract(&data_contract); };
-                        Arc::new(data_contract)
-                    },
-                    Ok(None) => return Err("data contract not found".to_string()),
-                    Err(e) => return Err(e.to_string())
-                }
-            }
-        };
-
-        let document_type = data_contract
-            .document_type_for_name(&document_type_str)
-            .expect("expected a profile document type");
-
         let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
-        let entropy_generator = DefaultEntropyGenerator;
-        let entropy = entropy_generator.generate().unwrap();
-        //let document_entropy = entropy_generator.generate().unwrap();
-        trace!("document_entropy: {:?}", entropy);
-        trace!("IdentityPublicKey: {:?}", identity_public_key);
-
-        // recreate the document using the same entropy value as when it is submitted below
-        let new_document_result = document_type.create_document_from_data(
-            document.properties().into(),
-            document.owner_id(),
+        put_document_to_platform(
+            SdkPtr(rust_sdk),
+            document,
+            data_contract_id,
+            document_type_str,
+            identity_public_key,
             block_height,
             core_block_height,
-            entropy,
-            PlatformVersion::latest()
-        );
-
-        let new_document = match new_document_result {
-            Ok(doc) => doc,
-            Err(e) => return Err(e.to_string())
-        };
-        let request_settings = unsafe { (*rust_sdk).get_request_settings() };
-
-        let settings = PutSettings {
-            request_settings,
-            identity_nonce_stale_time_s: None,
-            user_fee_increase: None,
-        };
-
-        trace!("Call Document::put_to_platform_and_wait_for_response");
-        let data_contract_cache = unsafe {&(*rust_sdk).data_contract_cache.clone() };
-        let extra_retries = settings.request_settings.retries.unwrap_or_else(|| 5usize);
-        let transition = put_document_with_retry(
-            sdk.clone(),
-            data_contract_cache.clone(),
-            new_document.clone(),
-            document_type.to_owned_document_type(),
-            entropy.clone(),
-            identity_public_key.clone(),
-            signer,
-            settings,
-            extra_retries
-        ).await.or_else(|err|Err(err.to_string()))?;
-
-        let result_document = wait_for_response_concurrent(
-            &new_document,
-            &sdk,
-            transition.clone(),
-            data_contract.clone(),
-            settings
-        ).await.or_else(|err|Err(err.to_string()))?;
+            signer
+        ).await
+    })
+}
 
-        Ok(result_document)
+/// Non-blocking version of [put_document_sdk]
+///
+/// Returns the request id, the result is [RequestResult::Document].
+#[ferment_macro::export]
+pub fn put_document_sdk_async(
+    rust_sdk: *mut DashSdk,
+    document: Document,
+    data_contract_id: Identifier,
+    document_type_str: String,
+    identity_public_key: IdentityPublicKey,
+    block_height: BlockHeight,
+    core_block_height: CoreBlockHeight,
+    signer_context: usize,
+    signer_callback: u64,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        put_document_to_platform(
+            rust_sdk,
+            document,
+            data_contract_id,
+            document_type_str,
+            identity_public_key,
+            block_height,
+            core_block_height,
+            signer
+        ).await.map(RequestResult::Document)
     })
 }
 
+/// Create a new document from the properties of `document`, put it to platform
+/// and wait for the result
+pub async fn put_document_to_platform(
+    rust_sdk: SdkPtr,
+    document: Document,
+    data_contract_id: Identifier,
+    document_type_str: String,
+    identity_public_key: IdentityPublicKey,
+    block_height: BlockHeight,
+    core_block_height: CoreBlockHeight,
+    signer: CallbackSigner
+) -> Result<Document, PlatformError> {
+    trace!("Setting up SDK");
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+
+    trace!("Finished SDK, {:?}", sdk);
+    trace!("Set up entropy, data contract and signer");
+
+    let data_contract = unsafe { (*rust_sdk.0).get_or_fetch_data_contract(&data_contract_id) }.await?;
+
+    let document_type = data_contract
+        .document_type_for_name(&document_type_str)?;
+
+    let entropy_generator = DefaultEntropyGenerator;
+    let entropy = entropy_generator.generate().unwrap();
+    trace!("document_entropy: {:?}", entropy);
+    trace!("IdentityPublicKey: {:?}", identity_public_key);
+
+    // recreate the document using the same entropy value as when it is submitted below
+    let new_document_result = document_type.create_document_from_data(
+        document.properties().into(),
+        document.owner_id(),
+        block_height,
+        core_block_height,
+        entropy,
+        PlatformVersion::latest()
+    );
+
+    let new_document = match new_document_result {
+        Ok(doc) => doc,
+        Err(e) => return Err(e.into())
+    };
+    let request_settings = unsafe { (*rust_sdk.0).get_request_settings() };
+
+    let settings = PutSettings {
+        request_settings,
+        identity_nonce_stale_time_s: None,
+        user_fee_increase: None,
+    };
+
+    trace!("Call Document::put_to_platform_and_wait_for_response");
+    let data_contract_cache = unsafe {&(*rust_sdk.0).data_contract_cache.clone() };
+    let extra_retries = settings.request_settings.retries.unwrap_or_else(|| 5usize);
+    let recorder = unsafe { (*rust_sdk.0).get_request_recorder() };
+    let transition = put_document_with_retry(
+        sdk.clone(),
+        data_contract_cache.clone(),
+        new_document.clone(),
+        document_type.to_owned_document_type(),
+        entropy.clone(),
+        identity_public_key.clone(),
+        signer,
+        settings,
+        recorder.clone(),
+        extra_retries
+    ).await?;
+
+    let result_document = wait_for_response_concurrent(
+        &new_document,
+        &sdk,
+        transition.clone(),
+        data_contract.clone(),
+        settings,
+        unsafe { (*rust_sdk.0).wait_settings },
+        &recorder
+    ).await?;
+
+    Ok(result_document)
+}
+
 fn replace_document_with_retry(
     sdk: Arc<Sdk>,
     data_contract_cache: Arc<Cache<Identifier, DataContract>>,
@@ -620,8 +1089,9 @@ fn replace_document_with_retry(
     identity_public_key: IdentityPublicKey,
     signer_callback: CallbackSigner,
     put_settings: PutSettings,
+    recorder: RequestRecorder,
     retries_left: usize,
-) -> BoxFuture<'static, Result<StateTransition, Error>> {
+) -> BoxFuture<'static, Result<StateTransition, PlatformError>> {
     Box::pin(async move {
         match new_document.replace_on_platform(
             &sdk,
@@ -632,8 +1102,10 @@ fn replace_document_with_retry(
         ).await {
             Ok(documents) => Ok(documents),
             Err(error) => {
+                recorder.record_error(&error);
+                let error = PlatformError::from(error);
                 if retries_left > 1 {
-                    if error.to_string().contains("contract not found error") {
+                    if let PlatformError::DataContractNotFound(_) = error {
                         if (data_contract_cache.get(&document_type.data_contract_id()) != None) {
                             return replace_document_with_retry(
                                 sdk,
@@ -643,6 +1115,7 @@ fn replace_document_with_retry(
                                 identity_public_key,
                                 signer_callback,
                                 put_settings,
+                                recorder,
                                 retries_left - 1
                             ).await;
                         }
@@ -665,73 +1138,341 @@ pub fn replace_document_sdk(
     core_block_height: CoreBlockHeight,
     signer_context: usize,
     signer_callback: u64
-) -> Result<Document, String> {
+) -> Result<Document, PlatformError> {
     let rt = unsafe { (*rust_sdk).get_runtime() };
 
 
     // Execute the async block using the Tokio runtime
     rt.block_on(async {
-        // Your async code here
-        let cfg = Config::new();
-        trace!("Setting up SDK");
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
-        trace!("Finished SDK, {:?}", sdk);
-        trace!("Set up entropy, data contract and signer");
-
-        let data_contract = match unsafe { (*rust_sdk).get_data_contract(&data_contract_id) } {
-            Some(data_contract) => data_contract.clone(),
-            None => {
-                let request_settings = unsafe { (*rust_sdk).get_request_settings() };
-                match (DataContract::fetch_with_settings(&sdk, data_contract_id.clone(), request_settings)
-                    .await) {
-                    Ok(Some(data_contract)) => {
-                        unsafe { (*rust_sdk).add_data_contract(&data_contract); };
-                        Arc::new(data_contract)
-                    },
-                    Ok(None) => return Err("data contract not found".to_string()),
-                    Err(e) => return Err(e.to_string())
-                }
-            }
-        };
-
-        let document_type = data_contract
-            .document_type_for_name(&document_type_str)
-            .expect("expected a profile document type");
-
         let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        replace_document_on_platform(
+            SdkPtr(rust_sdk),
+            document,
+            data_contract_id,
+            document_type_str,
+            identity_public_key,
+            signer
+        ).await
+    })
+}
 
-        trace!("IdentityPublicKey: {:?}", identity_public_key);
-        let request_settings = unsafe { (*rust_sdk).get_request_settings() };
-
-        let settings = PutSettings {
-            request_settings,
-            identity_nonce_stale_time_s: None,
-            user_fee_increase: None,
-        };
+/// Non-blocking version of [replace_document_sdk]
+///
+/// Returns the request id, the result is [RequestResult::Document].
+#[ferment_macro::export]
+pub fn replace_document_sdk_async(
+    rust_sdk: *mut DashSdk,
+    document: Document,
+    data_contract_id: Identifier,
+    document_type_str: String,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        replace_document_on_platform(
+            rust_sdk,
+            document,
+            data_contract_id,
+            document_type_str,
+            identity_public_key,
+            signer
+        ).await.map(RequestResult::Document)
+    })
+}
 
-        trace!("Call Document::replace_on_platform_and_wait_for_response");
+/// Replace `document` on platform and wait for the result
+pub async fn replace_document_on_platform(
+    rust_sdk: SdkPtr,
+    document: Document,
+    data_contract_id: Identifier,
+    document_type_str: String,
+    identity_public_key: IdentityPublicKey,
+    signer: CallbackSigner
+) -> Result<Document, PlatformError> {
+    trace!("Setting up SDK");
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    trace!("Finished SDK, {:?}", sdk);
+    trace!("Set up entropy, data contract and signer");
+
+    let data_contract = unsafe { (*rust_sdk.0).get_or_fetch_data_contract(&data_contract_id) }.await?;
+
+    let document_type = data_contract
+        .document_type_for_name(&document_type_str)?;
+
+    trace!("IdentityPublicKey: {:?}", identity_public_key);
+    let request_settings = unsafe { (*rust_sdk.0).get_request_settings() };
+
+    let settings = PutSettings {
+        request_settings,
+        identity_nonce_stale_time_s: None,
+        user_fee_increase: None,
+    };
+
+    trace!("Call Document::replace_on_platform_and_wait_for_response");
+
+    let data_contract_cache = unsafe {&(*rust_sdk.0).data_contract_cache.clone() };
+    let extra_retries = settings.request_settings.retries.unwrap_or_else(|| 5usize);
+    let recorder = unsafe { (*rust_sdk.0).get_request_recorder() };
+    let transition = replace_document_with_retry(
+        sdk.clone(),
+        data_contract_cache.clone(),
+        document.clone(),
+        document_type.to_owned_document_type(),
+        identity_public_key.clone(),
+        signer,
+        settings,
+        recorder.clone(),
+        extra_retries
+    ).await?;
+
+    let result_document = wait_for_response_concurrent(
+        &document,
+        &sdk,
+        transition.clone(),
+        data_contract,
+        settings,
+        unsafe { (*rust_sdk.0).wait_settings },
+        &recorder
+    ).await?;
+
+    Ok(result_document)
+}
 
-        let data_contract_cache = unsafe {&(*rust_sdk).data_contract_cache.clone() };
-        let extra_retries = settings.request_settings.retries.unwrap_or_else(|| 5usize);
-        let transition = replace_document_with_retry(
-            sdk.clone(),
-            data_contract_cache.clone(),
-            document.clone(),
-            document_type.to_owned_document_type(),
-            identity_public_key.clone(),
-            signer,
-            settings,
-            extra_retries
-        ).await.or_else(|err|Err(err.to_string()))?;
+async fn delete_document_from_platform(
+    sdk: &Sdk,
+    document: &Document,
+    document_type: &DocumentType,
+    identity_public_key: &IdentityPublicKey,
+    signer: &CallbackSigner,
+    settings: PutSettings,
+) -> Result<StateTransition, Error> {
+    let new_identity_contract_nonce = sdk.get_identity_contract_nonce(
+        document.owner_id(),
+        document_type.data_contract_id(),
+        true,
+        Some(settings),
+    ).await?;
+
+    let transition = DocumentsBatchTransition::new_document_deletion_transition_from_document(
+        document.clone(),
+        document_type.as_ref(),
+        identity_public_key,
+        new_identity_contract_nonce,
+        settings.user_fee_increase.unwrap_or_default(),
+        signer,
+        sdk.version(),
+        None,
+        None,
+        None,
+    )?;
+
+    let request = transition.broadcast_request_for_state_transition()?;
+    request.clone().execute(sdk, settings.request_settings).await?;
+
+    Ok(transition)
+}
 
-        let result_document = wait_for_response_concurrent(
-            &document,
+fn delete_document_with_retry(
+    sdk: Arc<Sdk>,
+    data_contract_cache: Arc<Cache<Identifier, DataContract>>,
+    document: Document,
+    document_type: DocumentType,
+    identity_public_key: IdentityPublicKey,
+    signer_callback: CallbackSigner,
+    put_settings: PutSettings,
+    recorder: RequestRecorder,
+    retries_left: usize,
+) -> BoxFuture<'static, Result<StateTransition, PlatformError>> {
+    Box::pin(async move {
+        match delete_document_from_platform(
             &sdk,
-            transition.clone(),
-            data_contract,
-            settings
-        ).await.or_else(|err|Err(err.to_string()))?;
+            &document,
+            &document_type,
+            &identity_public_key,
+            &signer_callback,
+            put_settings
+        ).await {
+            Ok(transition) => Ok(transition),
+            Err(error) => {
+                recorder.record_error(&error);
+                let error = PlatformError::from(error);
+                if retries_left > 1 {
+                    if let PlatformError::DataContractNotFound(_) = error {
+                        if (data_contract_cache.get(&document_type.data_contract_id()) != None) {
+                            return delete_document_with_retry(
+                                sdk,
+                                data_contract_cache,
+                                document,
+                                document_type,
+                                identity_public_key,
+                                signer_callback,
+                                put_settings,
+                                recorder,
+                                retries_left - 1
+                            ).await;
+                        }
+                    }
+                }
+                Err(error)
+            }
+        }
+    })
+}
+
+/// Delete a document from platform and wait for the result
+///
+/// The document type must allow deletion (`canBeDeleted`).  Returns the id of the deleted document.
+#[ferment_macro::export]
+pub fn delete_document_sdk(
+    rust_sdk: *mut DashSdk,
+    document: Document,
+    data_contract_id: Identifier,
+    document_type_str: String,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<Identifier, PlatformError> {
+    let rt = unsafe { (*rust_sdk).get_runtime() };
 
-        Ok(result_document)
+    rt.block_on(async {
+        let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+        delete_document_from_platform_and_wait(
+            SdkPtr(rust_sdk),
+            document,
+            data_contract_id,
+            document_type_str,
+            identity_public_key,
+            signer
+        ).await
     })
 }
+
+/// Non-blocking version of [delete_document_sdk]
+///
+/// Returns the request id, the result is [RequestResult::Identifier].
+#[ferment_macro::export]
+pub fn delete_document_sdk_async(
+    rust_sdk: *mut DashSdk,
+    document: Document,
+    data_contract_id: Identifier,
+    document_type_str: String,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        delete_document_from_platform_and_wait(
+            rust_sdk,
+            document,
+            data_contract_id,
+            document_type_str,
+            identity_public_key,
+            signer
+        ).await.map(RequestResult::Identifier)
+    })
+}
+
+pub async fn delete_document_from_platform_and_wait(
+    rust_sdk: SdkPtr,
+    document: Document,
+    data_contract_id: Identifier,
+    document_type_str: String,
+    identity_public_key: IdentityPublicKey,
+    signer: CallbackSigner
+) -> Result<Identifier, PlatformError> {
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    let data_contract = unsafe { (*rust_sdk.0).get_or_fetch_data_contract(&data_contract_id) }.await?;
+
+    let document_type = data_contract
+        .document_type_for_name(&document_type_str)?;
+
+    trace!("IdentityPublicKey: {:?}", identity_public_key);
+    let request_settings = unsafe { (*rust_sdk.0).get_request_settings() };
+
+    let settings = PutSettings {
+        request_settings,
+        identity_nonce_stale_time_s: None,
+        user_fee_increase: None,
+    };
+
+    trace!("Call delete_document_from_platform");
+    let data_contract_cache = unsafe { (*rust_sdk.0).data_contract_cache.clone() };
+    let extra_retries = settings.request_settings.retries.unwrap_or_else(|| 5usize);
+    let recorder = unsafe { (*rust_sdk.0).get_request_recorder() };
+    let transition = delete_document_with_retry(
+        sdk.clone(),
+        data_contract_cache,
+        document.clone(),
+        document_type.to_owned_document_type(),
+        identity_public_key,
+        signer,
+        settings,
+        recorder.clone(),
+        extra_retries
+    ).await?;
+
+    match wait_for_state_transition_result_concurrent(&sdk, &transition, request_settings, unsafe { (*rust_sdk.0).wait_settings }, &recorder).await? {
+        StateTransitionProofResult::VerifiedDocuments(documents) => {
+            match documents.get(&document.id()) {
+                Some(None) => Ok(document.id()),
+                Some(Some(_)) => Err(PlatformError::ProofVerificationFailed(
+                    format!("document {} was not deleted", document.id())
+                )),
+                None => Err(PlatformError::ProofVerificationFailed(
+                    format!("document {} is missing from the proof", document.id())
+                ))
+            }
+        }
+        result => Err(PlatformError::ProofVerificationFailed(
+            format!("unexpected proof result: {:?}", result)
+        ))
+    }
+}
+
+#[test]
+fn wait_concurrent_test() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let rt = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to create a runtime");
+    let wait_settings = WaitSettings { requests: 3, confirmations: 2 };
+
+    // one of the three waits fails, the other two confirm the result
+    let attempts = AtomicUsize::new(0);
+    let result: Result<usize, PlatformError> = rt.block_on(wait_concurrent("test", wait_settings, || {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+        async move {
+            if attempt == 0 {
+                Err(PlatformError::Timeout("wait timed out".to_string()))
+            } else {
+                Ok(attempt)
+            }
+        }
+    }));
+    assert_eq!(result, Ok(2));
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    // two of the three waits fail, the last error is returned
+    let attempts = AtomicUsize::new(0);
+    let result: Result<usize, PlatformError> = rt.block_on(wait_concurrent("test", wait_settings, || {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+        async move {
+            if attempt < 2 {
+                Err(PlatformError::Timeout(format!("wait {} timed out", attempt)))
+            } else {
+                Ok(attempt)
+            }
+        }
+    }));
+    assert_eq!(result, Err(PlatformError::Timeout("wait 1 timed out".to_string())));
+}
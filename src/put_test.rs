@@ -31,25 +31,13 @@ use rand::random;
 use simple_signer::signer::SimpleSigner;
 use tokio::runtime::Builder;
 use tracing::trace;
-use crate::config::{Config, EntryPoint};
+use crate::config::{Config, EntryPoint, PlatformNetwork};
 use crate::logs::setup_logs;
-use crate::put::{get_wait_result_error, wait_for_response_concurrent};
+use crate::health::{NodeHealthTracker, RequestRecorder};
+use crate::put::{delete_document_sdk, get_wait_result_error, put_document_sdk, wait_for_response_concurrent, WaitSettings};
 use dash_sdk::Error;
 use crate::sdk::{create_dash_sdk_using_core_testnet};
-
-fn get_salted_domain_hash(
-    pre_order_salt_raw: &[u8],
-    full_name: &str
-) -> [u8; 32] {
-    let mut baos = Vec::with_capacity(pre_order_salt_raw.len() + full_name.len());
-    baos.write_all(pre_order_salt_raw).expect("Writing to buffer failed");
-    baos.write_all(full_name.as_bytes()).expect("Writing to buffer failed");
-
-    sha256d::Hash::hash(&baos.as_slice()).into()
-}
-
-
-
+use crate::dpns::{get_salted_domain_hash, new_document};
 
 #[test]
 fn test_put_documents_for_username() {
@@ -214,7 +202,9 @@ fn test_put_documents_for_username() {
             &sdk,
             preorder_transition.clone(),
             data_contract.clone().into(),
-            settings
+            settings,
+            WaitSettings::default(),
+            &RequestRecorder::new(Arc::new(NodeHealthTracker::default()), &PlatformNetwork::Testnet)
         ).await.or_else(|err|Err(ProtocolError::Generic(err.to_string())))?;
 
         let domain_document_type = data_contract
@@ -337,4 +327,67 @@ fn test_put_txmetadata_contract() {
         Ok(data_contract) => tracing::info!("Success!\n{}: {:?}", data_contract.id(), data_contract),
         Err(err) => panic!("{:?}", err.to_string())
     };
-}
\ No newline at end of file
+}
+// signs with the private key of the AUTHENTICATION key of 7Yowk46VwwHqmD5yZyyygggh937aP6h2UW7aQWBdWpM5
+extern "C" fn test_signer_callback(_context: usize, _key_data: * const u8, _key_len: u32, data: * const u8, data_len: u32, result: * mut u8) -> u32 {
+    let private_key = hex::decode("a7285a6108fcd2a7b64060cbec68dddaf70c2d0514d8e0a447c8c933aef11b81").expect("Decoding failed");
+    let data = unsafe { std::slice::from_raw_parts(data, data_len as usize) };
+    match dashcore::signer::sign(data, private_key.as_slice()) {
+        Ok(signature) => {
+            unsafe { std::ptr::copy_nonoverlapping(signature.as_ptr(), result, signature.len()) };
+            signature.len() as u32
+        }
+        Err(_) => 0
+    }
+}
+
+#[test]
+fn test_put_and_delete_preorder_document() {
+    let entropy_generator = DefaultEntropyGenerator;
+    let owner_id = Identifier::from_string("7Yowk46VwwHqmD5yZyyygggh937aP6h2UW7aQWBdWpM5", Encoding::Base58).expect("identifier");
+    let identity_public_key = IdentityPublicKey::V0(
+        IdentityPublicKeyV0 {
+            id: 1,
+            purpose: Purpose::AUTHENTICATION,
+            security_level: SecurityLevel::HIGH,
+            contract_bounds: None,
+            key_type: KeyType::ECDSA_SECP256K1,
+            read_only: false,
+            data: BinaryData::from_string("AmM7AZIyWC1uOj0/3lFManLaTAK3RVToY+1yMvbpkvDa", Encoding::Base64).unwrap(),
+            disabled_at: None,
+        }
+    );
+    let preorder_salt = entropy_generator.generate().unwrap();
+    let mut preorder_props: BTreeMap<String, Value> = BTreeMap::new();
+    preorder_props.insert(
+        "saltedDomainHash".to_string(),
+        Value::Bytes32(get_salted_domain_hash(preorder_salt.as_slice(), "my-un1t-test-de1ete.dash"))
+    );
+
+    let mut sdk = create_dash_sdk_using_core_testnet();
+    let dpns_contract_id = Identifier::from(dpns_contract::ID_BYTES);
+    let document = put_document_sdk(
+        &mut sdk,
+        new_document(owner_id, preorder_props),
+        dpns_contract_id,
+        "preorder".to_string(),
+        identity_public_key.clone(),
+        0,
+        0,
+        0,
+        test_signer_callback as usize as u64
+    ).expect("put preorder");
+
+    match delete_document_sdk(
+        &mut sdk,
+        document.clone(),
+        dpns_contract_id,
+        "preorder".to_string(),
+        identity_public_key,
+        0,
+        test_signer_callback as usize as u64
+    ) {
+        Ok(id) => assert_eq!(id, document.id()),
+        Err(err) => panic!("{}", err)
+    }
+}
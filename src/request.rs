@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use dpp::document::Document;
+use dpp::identity::identity::Identity;
+use dpp::voting::votes::Vote;
+use drive_proof_verifier::types::{Contenders, ContestedResources, VotePollsGroupedByTimestamp};
+use platform_value::Identifier;
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+use crate::config::EntryPoint;
+use crate::error::PlatformError;
+use crate::sdk::DashSdk;
+
+/// Called on a runtime thread when an async request has finished.  The result is obtained
+/// with `take_request_result`.  It is not called for cancelled requests.
+pub type RequestCompletionCallback = extern "C" fn(context: usize, request_id: u64, success: bool);
+
+/// Result of an async request
+#[derive(Clone, Debug)]
+#[ferment_macro::export]
+pub enum RequestResult {
+    Documents(Vec<Document>),
+    Document(Document),
+    Identifier(Identifier),
+    Identity(Identity),
+    Vote(Vote),
+    OptionalVote(Option<Vote>),
+    Balance(u64),
+    ContestedResources(ContestedResources),
+    Contenders(Contenders),
+    VotePolls(VotePollsGroupedByTimestamp),
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn RequestResult_clone(value: RequestResult) -> RequestResult {
+    value.clone()
+}
+
+/// `*mut DashSdk` that is moved into the futures of the async exports
+///
+/// The caller owns the `DashSdk` and must not use it after `destroy_dash_sdk`, which waits
+/// for every request that was started with [spawn_request] to stop before it is freed.
+/// The futures only use the thread safe fields of `DashSdk` through this pointer.
+#[derive(Clone, Copy)]
+pub struct SdkPtr(pub *mut DashSdk);
+
+unsafe impl Send for SdkPtr {}
+
+struct PendingRequest {
+    handle: Option<JoinHandle<()>>,
+    result: Option<Result<RequestResult, PlatformError>>,
+}
+
+/// Requests that were started by the async exports and have not been taken or cancelled
+#[derive(Default)]
+pub struct RequestRegistry {
+    next_request_id: AtomicU64,
+    requests: Mutex<HashMap<u64, PendingRequest>>,
+    /// Cancelled requests that may still be running
+    cancelled: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl RequestRegistry {
+    fn complete(&self, request_id: u64, result: Result<RequestResult, PlatformError>) -> bool {
+        let mut requests = self.requests.lock();
+        match requests.get_mut(&request_id) {
+            Some(request) => {
+                request.handle = None;
+                request.result = Some(result);
+                true
+            }
+            // the request was cancelled
+            None => false
+        }
+    }
+
+    fn take(&self, request_id: u64) -> Result<RequestResult, PlatformError> {
+        let mut requests = self.requests.lock();
+        match requests.get(&request_id) {
+            None => Err(PlatformError::NotFound(format!("request {}", request_id))),
+            Some(request) if request.result.is_none() => {
+                Err(PlatformError::InvalidInput(format!("request {} has not completed", request_id)))
+            }
+            Some(_) => requests.remove(&request_id).and_then(|request| request.result).unwrap()
+        }
+    }
+
+    fn cancel(&self, request_id: u64) -> bool {
+        let request = self.requests.lock().remove(&request_id);
+        match request {
+            Some(request) => {
+                if let Some(handle) = request.handle {
+                    handle.abort();
+                    // an aborted task stops at its next await, keep it until then
+                    let mut cancelled = self.cancelled.lock();
+                    cancelled.retain(|handle| !handle.is_finished());
+                    cancelled.push(handle);
+                }
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Abort every request that has not completed and drop all results
+    ///
+    /// Returns the aborted tasks, which may still be running until they reach their next await.
+    pub fn cancel_all(&self) -> Vec<JoinHandle<()>> {
+        let mut handles: Vec<JoinHandle<()>> = self.cancelled.lock().drain(..).collect();
+        for (_, request) in self.requests.lock().drain() {
+            if let Some(handle) = request.handle {
+                handle.abort();
+                handles.push(handle);
+            }
+        }
+        handles
+    }
+}
+
+/// Run `future` on the runtime of `rust_sdk` and return the id of the request
+///
+/// `completion_callback` is a `RequestCompletionCallback` that is called with
+/// `completion_context` once the result can be taken.  When it is 0 no callback is made
+/// and the caller polls with [take_request_result].
+pub fn spawn_request<F>(
+    rust_sdk: SdkPtr,
+    completion_context: usize,
+    completion_callback: u64,
+    future: F
+) -> u64
+    where F: Future<Output = Result<RequestResult, PlatformError>> + Send + 'static
+{
+    let callback: Option<RequestCompletionCallback> = if completion_callback == 0 {
+        None
+    } else {
+        Some(unsafe { std::mem::transmute(completion_callback as usize) })
+    };
+    let rt = unsafe { (*rust_sdk.0).get_runtime() };
+    let registry = unsafe { (*rust_sdk.0).requests.clone() };
+    let request_id = registry.next_request_id.fetch_add(1, Ordering::Relaxed) + 1;
+    registry.requests.lock().insert(request_id, PendingRequest { handle: None, result: None });
+
+    let task_registry = registry.clone();
+    let handle = rt.spawn(async move {
+        let result = future.await;
+        let success = result.is_ok();
+        if task_registry.complete(request_id, result) {
+            if let Some(callback) = callback {
+                callback(completion_context, request_id, success);
+            }
+        }
+    });
+
+    let mut requests = registry.requests.lock();
+    match requests.get_mut(&request_id) {
+        Some(request) => {
+            if request.result.is_none() {
+                request.handle = Some(handle);
+            }
+        }
+        // cancelled before the handle was stored
+        None => {
+            handle.abort();
+            registry.cancelled.lock().push(handle);
+        }
+    }
+    drop(requests);
+    tracing::trace!("spawn_request: started request {}", request_id);
+    request_id
+}
+
+/// Take the result of a completed request.  Each result can only be taken once.
+#[ferment_macro::export]
+pub fn take_request_result(rust_sdk: *mut DashSdk, request_id: u64) -> Result<RequestResult, PlatformError> {
+    unsafe { (*rust_sdk).requests.take(request_id) }
+}
+
+/// Cancel a request that was started by one of the async exports
+///
+/// Returns false if the request is unknown or its result was already taken.
+/// The completion callback is not called for a cancelled request.
+#[ferment_macro::export]
+pub fn cancel_request(rust_sdk: *mut DashSdk, request_id: u64) -> bool {
+    tracing::info!("cancel_request: {}", request_id);
+    unsafe { (*rust_sdk).requests.cancel(request_id) }
+}
+
+#[test]
+fn request_registry_test() {
+    let registry = RequestRegistry::default();
+    registry.requests.lock().insert(1, PendingRequest { handle: None, result: None });
+    assert!(registry.take(1).is_err());
+    assert!(registry.complete(1, Err(PlatformError::Timeout("test".to_string()))));
+    assert_eq!(registry.take(1).unwrap_err(), PlatformError::Timeout("test".to_string()));
+    assert_eq!(registry.take(1).unwrap_err(), PlatformError::NotFound("request 1".to_string()));
+
+    registry.requests.lock().insert(2, PendingRequest { handle: None, result: None });
+    assert!(registry.cancel(2));
+    assert!(!registry.complete(2, Err(PlatformError::Timeout("test".to_string()))));
+    assert!(!registry.cancel(2));
+}
+
+#[test]
+fn request_registry_cancel_all_test() {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to create a runtime");
+    let registry = RequestRegistry::default();
+    for request_id in 1..=2 {
+        let handle = rt.spawn(std::future::pending::<()>());
+        registry.requests.lock().insert(request_id, PendingRequest { handle: Some(handle), result: None });
+    }
+    assert!(registry.cancel(2));
+
+    let handles = registry.cancel_all();
+    assert_eq!(handles.len(), 2);
+    rt.block_on(async {
+        for handle in handles {
+            assert!(handle.await.unwrap_err().is_cancelled());
+        }
+    });
+    assert!(registry.cancel_all().is_empty());
+}
+
+#[test]
+fn sdk_ptr_test() {
+    // requests dereference the SDK from runtime worker threads
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_send<T: Send>() {}
+    assert_send_sync::<DashSdk>();
+    assert_send::<SdkPtr>();
+}
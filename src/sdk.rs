@@ -1,25 +1,40 @@
-use std::os::raw::c_void;
+use std::fmt;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 use std::time::Duration;
 use dash_sdk::{RequestSettings, Sdk};
+use dash_sdk::platform::Fetch;
 use dpp::data_contract::accessors::v0::DataContractV0Getters;
 use dpp::data_contract::DataContract;
 use ferment_interfaces::{boxed, unbox_any};
 use platform_value::Identifier;
+use platform_value::string_encoding::Encoding;
 use tokio::runtime::{Builder, Runtime};
-use crate::config::{Config, EntryPoint};
-use crate::logs::setup_logs;
+use crate::config::{Config, EntryPoint, PlatformNetwork};
+use crate::logs::{setup_logs, setup_logs_with_filter};
 use crate::provider::Cache;
+use crate::disk_cache::DiskCache;
+use crate::data_contracts::preload_system_data_contracts;
+use crate::error::PlatformError;
+use crate::request::RequestRegistry;
+use crate::put::WaitSettings;
+use crate::evonodes::{EvonodeList, EvonodeRefresher};
+use crate::health::{NodeHealthTracker, RequestRecorder};
+use parking_lot::RwLock;
 
 #[ferment_macro::opaque]
 pub struct DashSdk {
     pub config: Arc<Config>,
     pub runtime: Arc<Runtime>,
-    pub sdk: Arc<Sdk>,
-    pub context_provider_context: * const c_void,
+    pub sdk: Arc<RwLock<Arc<Sdk>>>,
+    pub context_provider_context: usize,
     pub data_contract_cache: Arc<Cache<Identifier, DataContract>>,
-    pub request_settings: RequestSettings
+    pub request_settings: RequestSettings,
+    pub wait_settings: WaitSettings,
+    pub requests: Arc<RequestRegistry>,
+    /// Replaced by [update_sdk_with_address_list] while requests may be running
+    pub evonode_refresher: Arc<RwLock<EvonodeRefresher>>,
+    pub options: Arc<RwLock<DashSdkOptions>>
 }
 
 impl DashSdk {
@@ -27,9 +42,39 @@ impl DashSdk {
     pub fn get_config(&self) -> Arc<Config> {
         self.config.clone()
     }
+    pub fn get_network(&self) -> PlatformNetwork {
+        self.config.network.clone()
+    }
     pub fn get_data_contract_cache(&self) -> Arc<Cache<Identifier, DataContract>> {
         self.data_contract_cache.clone()
     }
+
+    pub fn get_evonode_refresher(&self) -> EvonodeRefresher {
+        self.evonode_refresher.read().clone()
+    }
+    pub fn get_options(&self) -> DashSdkOptions {
+        self.options.read().clone()
+    }
+
+    /// Records the evonodes that failed requests in the node health report
+    pub fn get_request_recorder(&self) -> RequestRecorder {
+        RequestRecorder::new(self.evonode_refresher.read().health.clone(), &self.config.network)
+    }
+
+    /// Get a data contract from the cache or fetch it from Platform and add it to the cache
+    pub async fn get_or_fetch_data_contract(&self, data_contract_id: &Identifier) -> Result<Arc<DataContract>, PlatformError> {
+        if let Some(data_contract) = self.data_contract_cache.get(data_contract_id) {
+            return Ok(data_contract);
+        }
+        match DataContract::fetch_with_settings(&self.get_sdk(), data_contract_id.clone(), self.request_settings).await {
+            Ok(Some(data_contract)) => {
+                self.data_contract_cache.put(data_contract.id(), data_contract.clone());
+                Ok(Arc::new(data_contract))
+            },
+            Ok(None) => Err(PlatformError::DataContractNotFound(data_contract_id.to_string(Encoding::Base58))),
+            Err(e) => Err(e.into())
+        }
+    }
 }
 
 impl EntryPoint for DashSdk {
@@ -37,7 +82,7 @@ impl EntryPoint for DashSdk {
         self.runtime.clone()
     }
     fn get_sdk(&self) -> Arc<Sdk> {
-        self.sdk.clone()
+        self.sdk.read().clone()
     }
 
     fn get_data_contract(&self, identifier: &Identifier) -> Option<Arc<DataContract>> {
@@ -68,24 +113,241 @@ pub fn update_sdk_with_address_list(
 
     // Execute the async block using the Tokio runtime
     rt.block_on(async {
-        let cfg = unsafe { (*rust_sdk).get_config() };
-
-
-        let sdk = cfg.setup_api_with_callbacks_cache_list(
-            unsafe { (*rust_sdk).context_provider_context },
+        let options = DashSdkOptions {
             quorum_public_key_callback,
             data_contract_callback,
-            unsafe { (*rust_sdk).get_data_contract_cache() },
-            address_list
-        ).await;
+            ..unsafe { (*rust_sdk).get_options() }
+        };
+        let refresher = EvonodeRefresher {
+            options: options.clone(),
+            ..unsafe { (*rust_sdk).get_evonode_refresher() }
+        };
+        refresher.evonode_list.stop_tasks();
+        refresher.update_sdk(address_list).await;
 
         tracing::info!("sdk created");
 
-        unsafe { (*rust_sdk).sdk = sdk }
-
+        refresher.start(&rt);
+        unsafe {
+            *(*rust_sdk).evonode_refresher.write() = refresher;
+            *(*rust_sdk).options.write() = options;
+        }
     });
 }
 
+/// Options used to create a [DashSdk] with [create_dash_sdk_from_options]
+///
+/// Start with [default_dash_sdk_options] and change the fields that are needed.
+#[derive(Clone)]
+#[ferment_macro::export]
+pub struct DashSdkOptions {
+    pub network: PlatformNetwork,
+    /// Evonodes to connect to first, until the masternode list is available.
+    /// The default list of the network is used when empty
+    pub address_list: Vec<String>,
+    pub connect_timeout_ms: u64,
+    pub timeout_ms: u64,
+    pub retries: u32,
+    /// Ban evonodes that fail a request
+    pub ban_failed_address: bool,
+    pub data_contract_cache_size: u32,
+    pub quorum_public_key_cache_size: u32,
+    /// Tracing filter, such as `info,dash_sdk=trace`.  The default filter is used when empty
+    pub log_filter: String,
+    /// Dash Core RPC connection used for quorum public keys when there is no quorum callback.
+    /// Empty values are loaded from the env file of the network
+    pub core_ip: String,
+    pub core_port: u16,
+    pub core_user: String,
+    pub core_password: String,
+    /// Number of evonodes that are asked for the result of a state transition
+    pub wait_requests: u32,
+    /// Number of evonodes that must return the result of a state transition
+    pub wait_confirmations: u32,
+    pub context_provider_context: usize,
+    /// Quorum public keys are obtained from Dash Core when this is 0
+    pub quorum_public_key_callback: u64,
+    pub data_contract_callback: u64,
+    /// Directory where the SDK keeps files between sessions, such as the last known good
+    /// evonode list, data contracts and quorum public keys.  Nothing is saved when empty
+    pub cache_dir: String,
+    pub evonode_list_context: usize,
+    /// An `EvonodeListCallback` that provides the evonodes of the masternode list, 0 if not available
+    pub evonode_list_callback: u64,
+    /// How often the evonode list is refreshed with `evonode_list_callback`, 0 disables refreshing
+    pub evonode_refresh_interval_s: u64,
+    /// How often the health of the evonodes is checked, 0 disables health checks (the default)
+    pub health_check_interval_s: u64,
+    /// Number of the healthiest evonodes that the SDK uses, 0 to use all evonodes that are not banned
+    pub preferred_node_count: u32,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn DashSdkOptions_clone(value: DashSdkOptions) -> DashSdkOptions {
+    value.clone()
+}
+
+// the Dash Core credentials are not logged
+impl fmt::Debug for DashSdkOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redact = |value: &String| if value.is_empty() { "" } else { "<redacted>" };
+        f.debug_struct("DashSdkOptions")
+            .field("network", &self.network)
+            .field("address_list", &self.address_list)
+            .field("connect_timeout_ms", &self.connect_timeout_ms)
+            .field("timeout_ms", &self.timeout_ms)
+            .field("retries", &self.retries)
+            .field("ban_failed_address", &self.ban_failed_address)
+            .field("data_contract_cache_size", &self.data_contract_cache_size)
+            .field("quorum_public_key_cache_size", &self.quorum_public_key_cache_size)
+            .field("log_filter", &self.log_filter)
+            .field("core_ip", &self.core_ip)
+            .field("core_port", &self.core_port)
+            .field("core_user", &redact(&self.core_user))
+            .field("core_password", &redact(&self.core_password))
+            .field("wait_requests", &self.wait_requests)
+            .field("wait_confirmations", &self.wait_confirmations)
+            .field("context_provider_context", &self.context_provider_context)
+            .field("quorum_public_key_callback", &self.quorum_public_key_callback)
+            .field("data_contract_callback", &self.data_contract_callback)
+            .field("cache_dir", &self.cache_dir)
+            .field("evonode_list_context", &self.evonode_list_context)
+            .field("evonode_list_callback", &self.evonode_list_callback)
+            .field("evonode_refresh_interval_s", &self.evonode_refresh_interval_s)
+            .field("health_check_interval_s", &self.health_check_interval_s)
+            .field("preferred_node_count", &self.preferred_node_count)
+            .finish()
+    }
+}
+
+impl DashSdkOptions {
+    pub fn request_settings(&self) -> RequestSettings {
+        RequestSettings {
+            connect_timeout: Some(Duration::from_millis(self.connect_timeout_ms)),
+            timeout: Some(Duration::from_millis(self.timeout_ms)),
+            retries: Some(self.retries as usize),
+            ban_failed_address: Some(self.ban_failed_address),
+        }
+    }
+
+    pub fn wait_settings(&self) -> WaitSettings {
+        let requests = (self.wait_requests as usize).max(1);
+        WaitSettings {
+            requests,
+            confirmations: (self.wait_confirmations as usize).clamp(1, requests),
+        }
+    }
+}
+
+#[ferment_macro::export]
+pub fn default_dash_sdk_options(network: PlatformNetwork) -> DashSdkOptions {
+    let wait_settings = WaitSettings::default();
+    DashSdkOptions {
+        network,
+        address_list: vec![],
+        connect_timeout_ms: 10_000,
+        timeout_ms: 5_000,
+        retries: 5,
+        ban_failed_address: true,
+        data_contract_cache_size: 100,
+        quorum_public_key_cache_size: 100,
+        log_filter: String::new(),
+        core_ip: String::new(),
+        core_port: 0,
+        core_user: String::new(),
+        core_password: String::new(),
+        wait_requests: wait_settings.requests as u32,
+        wait_confirmations: wait_settings.confirmations as u32,
+        context_provider_context: 0,
+        quorum_public_key_callback: 0,
+        data_contract_callback: 0,
+        cache_dir: String::new(),
+        evonode_list_context: 0,
+        evonode_list_callback: 0,
+        evonode_refresh_interval_s: 600,
+        // probes cost data and battery on mobile, so they are enabled by the app
+        health_check_interval_s: 0,
+        preferred_node_count: 20,
+    }
+}
+
+#[ferment_macro::export]
+pub fn create_dash_sdk_from_options(options: DashSdkOptions) -> DashSdk {
+    if options.log_filter.is_empty() {
+        setup_logs();
+    } else {
+        setup_logs_with_filter(&options.log_filter);
+    }
+    let rt = Arc::new(
+        Builder::new_multi_thread()
+            .enable_all() // Enables all I/O and time drivers
+            .build()
+            .expect("Failed to create a runtime")
+    );
+    tracing::info!("create_dash_sdk_from_options({:?})", options);
+
+    rt.block_on(async {
+        let cfg = Config::from_options(&options);
+        tracing::info!("config created");
+        let cache_size = NonZeroUsize::new(options.data_contract_cache_size as usize)
+            .unwrap_or(NonZeroUsize::new(100).expect("Non Zero"));
+        let quorum_public_keys_cache_size = NonZeroUsize::new(options.quorum_public_key_cache_size as usize)
+            .unwrap_or(NonZeroUsize::new(100).expect("Non Zero"));
+        let disk_cache = DiskCache::open(&options.cache_dir, &options.network, options.network.platform_version());
+        let data_contract_cache = Arc::new(match &disk_cache {
+            Some(disk_cache) => Cache::with_store(cache_size, disk_cache.clone()),
+            None => Cache::new(cache_size)
+        });
+        let quorum_public_keys_cache = Arc::new(match &disk_cache {
+            Some(disk_cache) => Cache::with_store(quorum_public_keys_cache_size, disk_cache.clone()),
+            None => Cache::new(quorum_public_keys_cache_size)
+        });
+        let platform_version = match &disk_cache {
+            Some(disk_cache) => disk_cache.platform_version(),
+            None => options.network.platform_version()
+        };
+        preload_system_data_contracts(&data_contract_cache, platform_version);
+        let health = Arc::new(NodeHealthTracker::default());
+        let evonode_list = Arc::new(EvonodeList::new(&options));
+        let preferred = health.preferred_nodes(&evonode_list.get(), &vec![], options.preferred_node_count as usize);
+        let sdk = cfg.setup_api_with_options(
+            &DashSdkOptions {
+                address_list: preferred.clone(),
+                ..options.clone()
+            },
+            data_contract_cache.clone(),
+            quorum_public_keys_cache.clone()
+        ).await;
+        evonode_list.set_active(preferred);
+        let config = Arc::new(cfg);
+        let sdk = Arc::new(RwLock::new(sdk));
+        let evonode_refresher = EvonodeRefresher {
+            config: config.clone(),
+            options: options.clone(),
+            data_contract_cache: data_contract_cache.clone(),
+            quorum_public_keys_cache,
+            disk_cache,
+            sdk: sdk.clone(),
+            evonode_list,
+            health,
+        };
+        evonode_refresher.start(&rt);
+        DashSdk {
+            config,
+            runtime: rt.clone(),
+            sdk,
+            context_provider_context: options.context_provider_context,
+            data_contract_cache: data_contract_cache,
+            requests: Arc::new(RequestRegistry::default()),
+            request_settings: options.request_settings(),
+            wait_settings: options.wait_settings(),
+            evonode_refresher: Arc::new(RwLock::new(evonode_refresher)),
+            options: Arc::new(RwLock::new(options))
+        }
+    })
+}
+
 pub fn create_dash_sdk_using_core_testnet() -> DashSdk {
     create_dash_sdk(0, 0, true)
 }
@@ -116,54 +378,14 @@ pub fn create_dash_sdk_with_context(
     timeout: usize,
     retries: usize
 ) -> DashSdk {
-    setup_logs();
-    let rt = Arc::new(
-        Builder::new_multi_thread()
-            .enable_all() // Enables all I/O and time drivers
-            .build()
-            .expect("Failed to create a runtime")
-    );
-    tracing::info!("create_dash_sdk_with_context({}, {}, {}, is_testnet{}, ...)",
+    create_dash_sdk_from_options(DashSdkOptions {
+        connect_timeout_ms: connect_timeout as u64 * 1000,
+        timeout_ms: timeout as u64 * 1000,
+        retries: retries as u32,
         context_provider_context,
         quorum_public_key_callback,
         data_contract_callback,
-        is_testnet
-    );
-
-    let context_provider_context: *const c_void = context_provider_context as *const c_void;
-    rt.block_on(async {
-        let cfg = if is_testnet {
-            Config::new_testnet()
-        } else {
-            Config::new_mainnet()
-        };
-        tracing::info!("config created");
-        let data_contract_cache = Arc::new(Cache::new(NonZeroUsize::new(100).expect("Non Zero")));
-        let sdk = if quorum_public_key_callback != 0 {
-            // use the callbacks to obtain quorum public keys
-            cfg.setup_api_with_callbacks_cache(
-                context_provider_context.clone(),
-                quorum_public_key_callback,
-                data_contract_callback,
-                data_contract_cache.clone()
-            ).await
-        } else {
-            // use Dash Core for quorum public keys
-            cfg.setup_api().await
-        };
-        DashSdk {
-            config: Arc::new(cfg),
-            runtime: rt.clone(),
-            sdk: sdk,
-            context_provider_context,
-            data_contract_cache: data_contract_cache,
-            request_settings: RequestSettings {
-                connect_timeout: Some(Duration::from_secs(connect_timeout as u64)),
-                timeout: Some(Duration::from_secs(timeout as u64)),
-                retries: Some(retries),
-                ban_failed_address: Some(true),
-            }
-        }
+        ..default_dash_sdk_options(PlatformNetwork::from_is_testnet(is_testnet))
     })
 }
 
@@ -174,47 +396,29 @@ pub fn create_dash_sdk_using_single_evonode(
     data_contract_callback: u64,
     is_testnet: bool
 ) -> DashSdk {
-    setup_logs();
-    let rt = Arc::new(
-        Builder::new_multi_thread()
-            .enable_all() // Enables all I/O and time drivers
-            .build()
-            .expect("Failed to create a runtime")
-    );
-
-    // Execute the async block using the Tokio runtime
-    rt.block_on(async {
-        let cfg = if is_testnet {
-            Config::new_testnet()
-        } else {
-            Config::new_mainnet()
-        };
-        let data_contract_cache = Arc::new(Cache::new(NonZeroUsize::new(100).expect("Non Zero")));
-        let sdk = if quorum_public_key_callback != 0 {
-            // use the callbacks to obtain quorum public keys
-            cfg.setup_api_with_callbacks_cache_list(std::ptr::null(), quorum_public_key_callback, data_contract_callback, data_contract_cache.clone(), vec![evonode]).await
-        } else {
-            // use Dash Core for quorum public keys
-            cfg.setup_api_list(vec![evonode]).await
-        };
-        DashSdk {
-            config: Arc::new(cfg),
-            runtime: rt.clone(),
-            sdk: sdk,
-            context_provider_context: std::ptr::null(),
-            data_contract_cache: data_contract_cache,
-            request_settings: RequestSettings {
-                connect_timeout: Some(Duration::from_secs(5)),
-                timeout: Some(Duration::from_secs(5)),
-                retries: Some(0),
-                ban_failed_address: Some(false),
-            }
-        }
+    create_dash_sdk_from_options(DashSdkOptions {
+        address_list: vec![evonode],
+        connect_timeout_ms: 5_000,
+        timeout_ms: 5_000,
+        retries: 0,
+        ban_failed_address: false,
+        quorum_public_key_callback,
+        data_contract_callback,
+        ..default_dash_sdk_options(PlatformNetwork::from_is_testnet(is_testnet))
     })
 }
 
 #[ferment_macro::export]
 pub fn destroy_dash_sdk(rust_sdk: * mut DashSdk) {
+    // pending requests use rust_sdk, so they must have stopped before it is freed
+    let handles = unsafe { (*rust_sdk).requests.cancel_all() };
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    rt.block_on(async {
+        for handle in handles {
+            let _ = handle.await;
+        }
+    });
+    unsafe { (*rust_sdk).get_evonode_refresher().evonode_list.stop_tasks() };
     unsafe  { unbox_any(rust_sdk) };
 }
 
@@ -223,4 +427,29 @@ fn test_dash_sdk() {
     let my_sdk = create_dash_sdk_using_core_testnet();
     let my_boxed_sdk = boxed(my_sdk);
     destroy_dash_sdk(my_boxed_sdk);
-}
\ No newline at end of file
+}
+#[test]
+fn dash_sdk_options_test() {
+    let options = DashSdkOptions {
+        wait_requests: 5,
+        wait_confirmations: 10,
+        ..default_dash_sdk_options(PlatformNetwork::Testnet)
+    };
+    let wait_settings = options.wait_settings();
+    assert_eq!(wait_settings.requests, 5);
+    assert_eq!(wait_settings.confirmations, 5);
+    assert_eq!(options.request_settings().timeout, Some(Duration::from_secs(5)));
+}
+
+#[test]
+fn dash_sdk_options_debug_test() {
+    let options = DashSdkOptions {
+        core_user: "dashrpc".to_string(),
+        core_password: "rpcpassword".to_string(),
+        ..default_dash_sdk_options(PlatformNetwork::Testnet)
+    };
+    let debug = format!("{:?}", options);
+    assert!(!debug.contains("dashrpc"));
+    assert!(!debug.contains("rpcpassword"));
+    assert!(debug.contains("<redacted>"));
+}
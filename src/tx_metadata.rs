@@ -0,0 +1,550 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use dpp::document::{Document, DocumentV0Getters};
+use dpp::identity::identity_public_key::IdentityPublicKey;
+use drive::query::{OrderClause, WhereClause, WhereOperator};
+use platform_value::{Identifier, Value};
+use platform_value::string_encoding::Encoding;
+use crate::config::EntryPoint;
+use crate::dpns::new_document;
+use crate::encryption::{AES_IV_SIZE, decrypt_aes_256_cbc, encrypt_aes_256_cbc};
+use crate::error::PlatformError;
+use crate::fetch_document::{create_document_pager, document_pager_next_page};
+use crate::put::{CallbackSigner, put_document_to_platform};
+use crate::request::SdkPtr;
+use crate::sdk::DashSdk;
+
+pub const TX_METADATA_DOCUMENT_TYPE: &str = "tx_metadata";
+/// Limits of `encryptedMetadata` in the dashwallet contract
+pub const TX_METADATA_MIN_SIZE: usize = 32;
+pub const TX_METADATA_MAX_SIZE: usize = 4096;
+/// The largest plain text that fits in [TX_METADATA_MAX_SIZE] after the IV and padding are added
+const TX_METADATA_MAX_PLAIN_TEXT_SIZE: usize = TX_METADATA_MAX_SIZE - AES_IV_SIZE - 1;
+
+/// Version of the binary format written by [serialize_tx_metadata_items]
+pub const TX_METADATA_FORMAT_VERSION: u8 = 1;
+
+// bits of the flags byte that mark which optional fields of an item are present
+const FLAG_MEMO: u8 = 1 << 0;
+const FLAG_EXCHANGE_RATE: u8 = 1 << 1;
+const FLAG_CURRENCY_CODE: u8 = 1 << 2;
+const FLAG_TAX_CATEGORY: u8 = 1 << 3;
+const FLAG_SERVICE: u8 = 1 << 4;
+const KNOWN_FLAGS: u8 = FLAG_MEMO | FLAG_EXCHANGE_RATE | FLAG_CURRENCY_CODE | FLAG_TAX_CATEGORY | FLAG_SERVICE;
+
+/// Derives the 32 byte AES key for `encryption_key_index` from the identity public key
+/// `key_index` of the owner and writes it to `result`.  Returns 0 if the key could not be derived.
+pub type TxMetadataKeyDerivationCallback = extern "C" fn(context: usize, key_index: u32, encryption_key_index: u32, result: * mut u8) -> u32;
+
+/// Metadata that the wallet keeps for one transaction
+#[derive(Clone, Debug, PartialEq)]
+#[ferment_macro::export]
+pub struct TxMetadataItem {
+    pub tx_id: [u8; 32],
+    /// Time the metadata was last changed, in milliseconds
+    pub timestamp: u64,
+    pub memo: Option<String>,
+    /// Fiat value of 1 DASH when the transaction was made
+    pub exchange_rate: Option<f64>,
+    /// ISO 4217 code of the currency of `exchange_rate`, such as `USD`
+    pub currency_code: Option<String>,
+    /// Category for tax reports, such as `income` or `expense`
+    pub tax_category: Option<String>,
+    /// Merchant or service that the transaction was made with
+    pub service: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn TxMetadataItem_clone(value: TxMetadataItem) -> TxMetadataItem {
+    value.clone()
+}
+
+/// A decrypted `tx_metadata` document
+#[derive(Clone, Debug, PartialEq)]
+#[ferment_macro::export]
+pub struct TxMetadataDocument {
+    pub id: Identifier,
+    pub owner_id: Identifier,
+    pub created_at: u64,
+    pub key_index: u32,
+    pub encryption_key_index: u32,
+    pub items: Vec<TxMetadataItem>,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn TxMetadataDocument_clone(value: TxMetadataDocument) -> TxMetadataDocument {
+    value.clone()
+}
+
+/// Result of [publish_tx_metadata_sdk]
+///
+/// Publishing is not atomic: the batches are put one document at a time and publishing stops
+/// at the first failure.  `documents` were published before the failure and `unpublished_items`
+/// are the items of the failed batch and the batches after it, which can be passed to
+/// [publish_tx_metadata_sdk] to retry.  A batch that failed with [PlatformError::Timeout] may
+/// still have been published, [fetch_tx_metadata_sdk] shows whether its items are on Platform.
+#[derive(Clone, Debug)]
+#[ferment_macro::export]
+pub struct TxMetadataPublishResult {
+    pub documents: Vec<Document>,
+    pub unpublished_items: Vec<TxMetadataItem>,
+    /// The error that stopped publishing, None if all the items were published
+    pub error: Option<PlatformError>,
+}
+
+#[allow(non_snake_case)]
+#[ferment_macro::export]
+pub fn TxMetadataPublishResult_clone(value: TxMetadataPublishResult) -> TxMetadataPublishResult {
+    value.clone()
+}
+
+fn write_var_int(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn write_string(buffer: &mut Vec<u8>, value: &str) {
+    write_var_int(buffer, value.len() as u64);
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+fn write_item(buffer: &mut Vec<u8>, item: &TxMetadataItem) {
+    let mut flags = 0u8;
+    if item.memo.is_some() { flags |= FLAG_MEMO; }
+    if item.exchange_rate.is_some() { flags |= FLAG_EXCHANGE_RATE; }
+    if item.currency_code.is_some() { flags |= FLAG_CURRENCY_CODE; }
+    if item.tax_category.is_some() { flags |= FLAG_TAX_CATEGORY; }
+    if item.service.is_some() { flags |= FLAG_SERVICE; }
+
+    buffer.extend_from_slice(&item.tx_id);
+    write_var_int(buffer, item.timestamp);
+    buffer.push(flags);
+    if let Some(memo) = &item.memo {
+        write_string(buffer, memo);
+    }
+    if let Some(exchange_rate) = item.exchange_rate {
+        buffer.extend_from_slice(&exchange_rate.to_le_bytes());
+    }
+    if let Some(currency_code) = &item.currency_code {
+        write_string(buffer, currency_code);
+    }
+    if let Some(tax_category) = &item.tax_category {
+        write_string(buffer, tax_category);
+    }
+    if let Some(service) = &item.service {
+        write_string(buffer, service);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], PlatformError> {
+        if length > self.bytes.len() {
+            return Err(PlatformError::InvalidInput("tx metadata is truncated".to_string()));
+        }
+        let (bytes, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, PlatformError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_var_int(&mut self) -> Result<u64, PlatformError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(PlatformError::InvalidInput("tx metadata has an invalid var int".to_string()))
+    }
+
+    fn read_string(&mut self) -> Result<String, PlatformError> {
+        let length = self.read_var_int()? as usize;
+        String::from_utf8(self.read_bytes(length)?.to_vec())
+            .map_err(|e| PlatformError::InvalidInput(format!("tx metadata has an invalid string: {}", e)))
+    }
+
+    fn read_item(&mut self) -> Result<TxMetadataItem, PlatformError> {
+        let tx_id: [u8; 32] = self.read_bytes(32)?.try_into().expect("32 bytes");
+        let timestamp = self.read_var_int()?;
+        let flags = self.read_u8()?;
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(PlatformError::InvalidInput(format!("tx metadata has unknown fields: {:#04x}", flags)));
+        }
+        let memo = if flags & FLAG_MEMO != 0 { Some(self.read_string()?) } else { None };
+        let exchange_rate = if flags & FLAG_EXCHANGE_RATE != 0 {
+            Some(f64::from_le_bytes(self.read_bytes(8)?.try_into().expect("8 bytes")))
+        } else {
+            None
+        };
+        let currency_code = if flags & FLAG_CURRENCY_CODE != 0 { Some(self.read_string()?) } else { None };
+        let tax_category = if flags & FLAG_TAX_CATEGORY != 0 { Some(self.read_string()?) } else { None };
+        let service = if flags & FLAG_SERVICE != 0 { Some(self.read_string()?) } else { None };
+        Ok(TxMetadataItem { tx_id, timestamp, memo, exchange_rate, currency_code, tax_category, service })
+    }
+}
+
+/// Serialize `items` in the binary format that is stored in `encryptedMetadata`
+///
+/// The format is shared by the Android and iOS wallets.  All integers are unsigned LEB128
+/// var ints and strings are UTF-8 prefixed with their length.
+///
+/// ```text
+/// version: u8 (1), count: var int, items: count * {
+///     tx_id: [u8; 32], timestamp: var int, flags: u8,
+///     memo: string if flags & 0x01, exchange_rate: f64 little endian if flags & 0x02,
+///     currency_code: string if flags & 0x04, tax_category: string if flags & 0x08,
+///     service: string if flags & 0x10
+/// }
+/// ```
+pub fn serialize_tx_metadata_items(items: &[TxMetadataItem]) -> Vec<u8> {
+    let mut buffer = vec![TX_METADATA_FORMAT_VERSION];
+    write_var_int(&mut buffer, items.len() as u64);
+    for item in items {
+        write_item(&mut buffer, item);
+    }
+    buffer
+}
+
+/// Parse items that were serialized with [serialize_tx_metadata_items]
+pub fn deserialize_tx_metadata_items(bytes: &[u8]) -> Result<Vec<TxMetadataItem>, PlatformError> {
+    let mut reader = Reader { bytes };
+    let version = reader.read_u8()?;
+    if version != TX_METADATA_FORMAT_VERSION {
+        return Err(PlatformError::InvalidInput(format!("unsupported tx metadata version: {}", version)));
+    }
+    let count = reader.read_var_int()?;
+    let mut items = vec![];
+    for _ in 0..count {
+        items.push(reader.read_item()?);
+    }
+    if !reader.bytes.is_empty() {
+        return Err(PlatformError::InvalidInput(format!("tx metadata has {} extra bytes", reader.bytes.len())));
+    }
+    Ok(items)
+}
+
+/// Split `items` into batches that each fit in one `tx_metadata` document once they are
+/// serialized and encrypted
+pub fn batch_tx_metadata_items(items: Vec<TxMetadataItem>) -> Result<Vec<Vec<TxMetadataItem>>, PlatformError> {
+    let mut batches = vec![];
+    let mut batch: Vec<TxMetadataItem> = vec![];
+    for item in items {
+        batch.push(item);
+        if serialize_tx_metadata_items(&batch).len() > TX_METADATA_MAX_PLAIN_TEXT_SIZE {
+            let item = batch.pop().expect("item");
+            if batch.is_empty() {
+                return Err(PlatformError::InvalidInput(
+                    format!("the metadata of {} is too large", hex::encode(item.tx_id))
+                ));
+            }
+            batches.push(std::mem::replace(&mut batch, vec![item]));
+        }
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    Ok(batches)
+}
+
+fn derive_tx_metadata_key(
+    key_index: u32,
+    encryption_key_index: u32,
+    key_derivation_context: usize,
+    key_derivation_callback: u64
+) -> Result<[u8; 32], PlatformError> {
+    if key_derivation_callback == 0 {
+        return Err(PlatformError::InvalidInput("the key derivation callback is not set".to_string()));
+    }
+    let callback: TxMetadataKeyDerivationCallback = unsafe { std::mem::transmute(key_derivation_callback as usize) };
+    let mut key = [0u8; 32];
+    if callback(key_derivation_context, key_index, encryption_key_index, key.as_mut_ptr()) == 0 {
+        return Err(PlatformError::InvalidInput(
+            format!("failed to derive encryption key {} of key {}", encryption_key_index, key_index)
+        ));
+    }
+    Ok(key)
+}
+
+/// Encrypt with AES-CBC-256 and PKCS7 padding, the random IV is prepended to the cipher text
+pub fn encrypt_tx_metadata(key: &[u8; 32], plain_text: &[u8]) -> Vec<u8> {
+    encrypt_aes_256_cbc(key, plain_text)
+}
+
+/// Decrypt data that was encrypted with [encrypt_tx_metadata]
+pub fn decrypt_tx_metadata(key: &[u8; 32], encrypted: &[u8]) -> Result<Vec<u8>, PlatformError> {
+    if encrypted.len() < TX_METADATA_MIN_SIZE {
+        return Err(PlatformError::InvalidInput(format!("encrypted metadata is too short: {} bytes", encrypted.len())));
+    }
+    decrypt_aes_256_cbc(key, encrypted)
+}
+
+pub(crate) fn get_u32_property(document: &Document, name: &str) -> Result<u32, PlatformError> {
+    document.properties()
+        .get(name)
+        .ok_or(PlatformError::InvalidInput(format!("{} is missing", name)))?
+        .to_integer::<u32>()
+        .map_err(|e| PlatformError::InvalidInput(format!("{} is invalid: {}", name, e)))
+}
+
+fn decrypt_tx_metadata_document(
+    document: &Document,
+    key_derivation_context: usize,
+    key_derivation_callback: u64
+) -> Result<TxMetadataDocument, PlatformError> {
+    let key_index = get_u32_property(document, "keyIndex")?;
+    let encryption_key_index = get_u32_property(document, "encryptionKeyIndex")?;
+    let encrypted = document.properties()
+        .get("encryptedMetadata")
+        .ok_or(PlatformError::InvalidInput("encryptedMetadata is missing".to_string()))?
+        .to_binary_bytes()
+        .map_err(|e| PlatformError::InvalidInput(format!("encryptedMetadata is invalid: {}", e)))?;
+
+    let key = derive_tx_metadata_key(key_index, encryption_key_index, key_derivation_context, key_derivation_callback)?;
+    let plain_text = decrypt_tx_metadata(&key, &encrypted)?;
+    let items = deserialize_tx_metadata_items(&plain_text)?;
+
+    Ok(TxMetadataDocument {
+        id: document.id(),
+        owner_id: document.owner_id(),
+        created_at: document.created_at().unwrap_or_default(),
+        key_index,
+        encryption_key_index,
+        items,
+    })
+}
+
+/// Encrypt `items` with the key derived by `key_derivation_callback` and put them to
+/// platform as `tx_metadata` documents of the dashwallet contract
+///
+/// The items are split into as many documents as needed to respect the size limit of
+/// `encryptedMetadata`.  Errors that occur before any document is put, such as invalid items
+/// or a key that cannot be derived, are returned as `Err`; a failure while putting the documents
+/// is returned in [TxMetadataPublishResult] with the documents that were already published.
+#[ferment_macro::export]
+pub fn publish_tx_metadata_sdk(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    owner_id: Identifier,
+    items: Vec<TxMetadataItem>,
+    key_index: u32,
+    encryption_key_index: u32,
+    key_derivation_context: usize,
+    key_derivation_callback: u64,
+    identity_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64
+) -> Result<TxMetadataPublishResult, PlatformError> {
+    if items.is_empty() {
+        return Err(PlatformError::InvalidInput("there are no items to publish".to_string()));
+    }
+    let key = derive_tx_metadata_key(key_index, encryption_key_index, key_derivation_context, key_derivation_callback)?;
+    let batches = batch_tx_metadata_items(items)?;
+    let signer = CallbackSigner::new(signer_context, signer_callback)?;
+
+    let rt = unsafe { (*rust_sdk).get_runtime() };
+    Ok(rt.block_on(publish_tx_metadata_batches(batches, |batch| {
+        let encrypted = encrypt_tx_metadata(&key, &serialize_tx_metadata_items(&batch));
+        let mut properties: BTreeMap<String, Value> = BTreeMap::new();
+        properties.insert("keyIndex".to_string(), Value::U32(key_index));
+        properties.insert("encryptionKeyIndex".to_string(), Value::U32(encryption_key_index));
+        properties.insert("encryptedMetadata".to_string(), Value::Bytes(encrypted));
+
+        tracing::info!("publish_tx_metadata_sdk: put {} items", batch.len());
+        put_document_to_platform(
+            SdkPtr(rust_sdk),
+            new_document(owner_id, properties),
+            data_contract_id,
+            TX_METADATA_DOCUMENT_TYPE.to_string(),
+            identity_public_key.clone(),
+            0,
+            0,
+            signer.clone()
+        )
+    })))
+}
+
+/// Put the `batches` in order with `put` and stop at the first failure
+async fn publish_tx_metadata_batches<F, Fut>(
+    batches: Vec<Vec<TxMetadataItem>>,
+    mut put: F
+) -> TxMetadataPublishResult
+    where F: FnMut(&Vec<TxMetadataItem>) -> Fut, Fut: Future<Output = Result<Document, PlatformError>>
+{
+    let mut documents = vec![];
+    let mut batches = batches.into_iter();
+    while let Some(batch) = batches.next() {
+        match put(&batch).await {
+            Ok(document) => documents.push(document),
+            Err(error) => {
+                tracing::warn!("publish_tx_metadata_sdk: published {} documents before failing: {}", documents.len(), error);
+                return TxMetadataPublishResult {
+                    documents,
+                    unpublished_items: batch.into_iter().chain(batches.flatten()).collect(),
+                    error: Some(error),
+                };
+            }
+        }
+    }
+    TxMetadataPublishResult { documents, unpublished_items: vec![], error: None }
+}
+
+/// Fetch and decrypt the `tx_metadata` documents of `owner_id` that were created after
+/// `since` (milliseconds), oldest first
+///
+/// Documents that cannot be decrypted, such as those encrypted with a key that the
+/// callback cannot derive, are skipped.
+#[ferment_macro::export]
+pub fn fetch_tx_metadata_sdk(
+    rust_sdk: *mut DashSdk,
+    data_contract_id: Identifier,
+    owner_id: Identifier,
+    since: u64,
+    key_derivation_context: usize,
+    key_derivation_callback: u64
+) -> Result<Vec<TxMetadataDocument>, PlatformError> {
+    let mut pager = create_document_pager(
+        rust_sdk,
+        data_contract_id,
+        TX_METADATA_DOCUMENT_TYPE.to_string(),
+        vec![
+            WhereClause { field: "$ownerId".into(), value: Value::Identifier(owner_id.to_buffer()), operator: WhereOperator::Equal },
+            WhereClause { field: "$createdAt".into(), value: Value::U64(since), operator: WhereOperator::GreaterThan }
+        ],
+        vec![
+            OrderClause { field: "$createdAt".into(), ascending: true }
+        ],
+        100
+    )?;
+
+    let mut results = vec![];
+    while !pager.is_exhausted() {
+        for document in document_pager_next_page(rust_sdk, &mut pager)? {
+            match decrypt_tx_metadata_document(&document, key_derivation_context, key_derivation_callback) {
+                Ok(tx_metadata) => results.push(tx_metadata),
+                Err(e) => tracing::warn!("fetch_tx_metadata_sdk: skipping document {}: {}", document.id().to_string(Encoding::Base58), e)
+            }
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+fn test_tx_metadata_item(index: u8, memo: Option<String>) -> TxMetadataItem {
+    TxMetadataItem {
+        tx_id: [index; 32],
+        timestamp: 1_700_000_000_000 + index as u64,
+        memo,
+        exchange_rate: Some(28.5),
+        currency_code: Some("USD".to_string()),
+        tax_category: Some("expense".to_string()),
+        service: None,
+    }
+}
+
+#[test]
+fn tx_metadata_encryption_test() {
+    let key = [7u8; 32];
+    let items = vec![test_tx_metadata_item(1, Some("coffee".to_string()))];
+    let plain_text = serialize_tx_metadata_items(&items);
+    let encrypted = encrypt_tx_metadata(&key, &plain_text);
+    assert!(encrypted.len() >= TX_METADATA_MIN_SIZE);
+    assert_eq!(decrypt_tx_metadata(&key, &encrypted).unwrap(), plain_text);
+    assert!(decrypt_tx_metadata(&[8u8; 32], &encrypted).map(|decrypted| decrypted != plain_text).unwrap_or(true));
+
+    // callbacks that are not set are rejected instead of being called
+    assert!(matches!(derive_tx_metadata_key(1, 0, 0, 0), Err(PlatformError::InvalidInput(_))));
+    assert!(matches!(CallbackSigner::new(0, 0).map_err(PlatformError::from), Err(PlatformError::InvalidInput(_))));
+}
+
+#[test]
+fn tx_metadata_serialization_test() {
+    let items = vec![
+        test_tx_metadata_item(1, Some("coffee ☕".to_string())),
+        TxMetadataItem {
+            tx_id: [2; 32],
+            timestamp: 0,
+            memo: None,
+            exchange_rate: None,
+            currency_code: None,
+            tax_category: None,
+            service: Some("DashDirect".to_string()),
+        },
+    ];
+    let bytes = serialize_tx_metadata_items(&items);
+    assert_eq!(bytes[0], TX_METADATA_FORMAT_VERSION);
+    assert_eq!(deserialize_tx_metadata_items(&bytes).unwrap(), items);
+    assert_eq!(deserialize_tx_metadata_items(&serialize_tx_metadata_items(&[])).unwrap(), vec![]);
+
+    // the second item only has the service field
+    let second = &bytes[bytes.len() - 45..];
+    assert_eq!(second[..32], [2u8; 32]);
+    assert_eq!(second[32..34], [0, FLAG_SERVICE]);
+
+    assert!(deserialize_tx_metadata_items(&bytes[..bytes.len() - 1]).is_err());
+    let mut unknown_version = bytes.clone();
+    unknown_version[0] = 2;
+    assert!(deserialize_tx_metadata_items(&unknown_version).is_err());
+}
+
+#[test]
+fn batch_tx_metadata_items_test() {
+    let items: Vec<TxMetadataItem> = (0..100)
+        .map(|index| test_tx_metadata_item(index, Some("x".repeat(100))))
+        .collect();
+    let batches = batch_tx_metadata_items(items.clone()).unwrap();
+    assert!(batches.len() > 1);
+    for batch in &batches {
+        let encrypted = encrypt_tx_metadata(&[7u8; 32], &serialize_tx_metadata_items(batch));
+        assert!(encrypted.len() <= TX_METADATA_MAX_SIZE);
+    }
+    assert_eq!(batches.concat(), items);
+
+    assert!(batch_tx_metadata_items(vec![test_tx_metadata_item(1, Some("x".repeat(TX_METADATA_MAX_SIZE)))]).is_err());
+}
+
+#[test]
+fn publish_tx_metadata_batches_test() {
+    use dpp::document::DocumentV0;
+
+    let batches: Vec<Vec<TxMetadataItem>> = (0..3)
+        .map(|batch| vec![test_tx_metadata_item(batch * 2, None), test_tx_metadata_item(batch * 2 + 1, None)])
+        .collect();
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let put = |fail_at: usize| {
+        let mut count = 0;
+        move |_batch: &Vec<TxMetadataItem>| {
+            count += 1;
+            let result = if count == fail_at {
+                Err(PlatformError::Timeout("no response".to_string()))
+            } else {
+                Ok(Document::V0(DocumentV0::default()))
+            };
+            async move { result }
+        }
+    };
+
+    let result = rt.block_on(publish_tx_metadata_batches(batches.clone(), put(0)));
+    assert_eq!(result.documents.len(), 3);
+    assert!(result.unpublished_items.is_empty());
+    assert!(result.error.is_none());
+
+    // the items of the failed batch and of the batches after it are returned for a retry
+    let result = rt.block_on(publish_tx_metadata_batches(batches.clone(), put(2)));
+    assert_eq!(result.documents.len(), 1);
+    assert_eq!(result.unpublished_items, batches[1..].concat());
+    assert_eq!(result.error, Some(PlatformError::Timeout("no response".to_string())));
+}
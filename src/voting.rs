@@ -1,4 +1,3 @@
-use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use dapi_grpc::platform::v0::GetContestedResourceIdentityVotesRequest;
 use dash_sdk::platform::transition::put_document::PutDocument;
@@ -8,7 +7,6 @@ use dash_sdk::{Error, Sdk};
 use dash_sdk::platform::{Fetch, FetchMany};
 use dashcore::{base58, PrivateKey};
 use dpp::data_contract::accessors::v0::DataContractV0Getters;
-use dpp::data_contract::DataContract;
 use dpp::data_contract::document_type::accessors::DocumentTypeV0Getters;
 use dpp::document::Document;
 use dpp::identity::{Identity, identity_public_key::IdentityPublicKey, TimestampMillis};
@@ -38,6 +36,8 @@ use crate::config::{Config, EntryPoint};
 use crate::fetch_document::fetch_documents_with_query_and_sdk;
 use crate::put::{CallbackSigner, SignerCallback, wait_for_response_concurrent};
 use crate::sdk::{create_dash_sdk_using_core_testnet, DashSdk};
+use crate::error::PlatformError;
+use crate::request::{RequestResult, SdkPtr, spawn_request};
 
 #[ferment_macro::export]
 pub fn put_vote_to_platform(
@@ -47,43 +47,79 @@ pub fn put_vote_to_platform(
     voting_public_key: IdentityPublicKey,
     signer_context: usize,
     signer_callback: u64
-) -> Result<Vote, String> {
+) -> Result<Vote, PlatformError> {
 
     let rt = unsafe { (*rust_sdk).get_runtime() };
     rt.block_on(async {
-
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
         let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
-        let request_settings = unsafe { (*rust_sdk).get_request_settings() };
-
-        let settings = PutSettings {
-            request_settings,
-            identity_nonce_stale_time_s: None,
-            user_fee_increase: None,
-        };
+        put_vote_and_wait(SdkPtr(rust_sdk), vote, voter_pro_tx_hash, voting_public_key, signer).await
+    })
+}
 
-        tracing::info!("Call Vote::put_to_platform");
-
-        let masternode_vote_transition = vote.put_to_platform(
-            voter_pro_tx_hash,
-            &voting_public_key,
-            &sdk,
-            &signer,
-            Some(settings)
-        ).await.or_else(|err|Err(err.to_string()))?;
-        tracing::info!("Call Vote::wait_for_response");
-
-        let vote = <Vote as PutVote<SimpleSigner>>::wait_for_response::<'_, '_, '_>(
-            &vote,
-            masternode_vote_transition,
-            &sdk,
-            Some(settings)
-        ).await.or_else(|err|Err(err.to_string()))?;
-
-        Ok(vote)
+/// Non-blocking version of [put_vote_to_platform]
+///
+/// Returns the request id, the result is [RequestResult::Vote].
+#[ferment_macro::export]
+pub fn put_vote_to_platform_async(
+    rust_sdk: *mut DashSdk,
+    vote: Vote,
+    voter_pro_tx_hash: Identifier,
+    voting_public_key: IdentityPublicKey,
+    signer_context: usize,
+    signer_callback: u64,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let signer = CallbackSigner::new(signer_context, signer_callback).expect("signer");
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        put_vote_and_wait(rust_sdk, vote, voter_pro_tx_hash, voting_public_key, signer)
+            .await
+            .map(RequestResult::Vote)
     })
 }
 
+async fn put_vote_and_wait(
+    rust_sdk: SdkPtr,
+    vote: Vote,
+    voter_pro_tx_hash: Identifier,
+    voting_public_key: IdentityPublicKey,
+    signer: CallbackSigner
+) -> Result<Vote, PlatformError> {
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    let request_settings = unsafe { (*rust_sdk.0).get_request_settings() };
+
+    let settings = PutSettings {
+        request_settings,
+        identity_nonce_stale_time_s: None,
+        user_fee_increase: None,
+    };
+
+    tracing::info!("Call Vote::put_to_platform");
+
+    let recorder = unsafe { (*rust_sdk.0).get_request_recorder() };
+    let masternode_vote_transition = vote.put_to_platform(
+        voter_pro_tx_hash,
+        &voting_public_key,
+        &sdk,
+        &signer,
+        Some(settings)
+    ).await;
+    recorder.record_result(&masternode_vote_transition);
+    tracing::info!("Call Vote::wait_for_response");
+
+    let vote = <Vote as PutVote<SimpleSigner>>::wait_for_response::<'_, '_, '_>(
+        &vote,
+        masternode_vote_transition?,
+        &sdk,
+        Some(settings)
+    ).await;
+    recorder.record_result(&vote);
+    let vote = vote?;
+
+    Ok(vote)
+}
+
 // #[test]
 // fn put_vote_test() {
 //     let mut sdk = create_dash_sdk_using_core_testnet();
@@ -204,104 +240,134 @@ pub fn get_vote_contenders(
     index_values: Vec<Value>,
     document_type_name: String,
     contract_id: Identifier
-) -> Result<Contenders, String>{
+) -> Result<Contenders, PlatformError>{
 
     let rt = unsafe { (*rust_sdk).get_runtime() };
 
     // Execute the async block using the Tokio runtime
-    rt.block_on(async {
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
-
+    rt.block_on(fetch_vote_contenders(SdkPtr(rust_sdk), index_name, index_values, document_type_name, contract_id))
+}
 
-        let query = ContestedDocumentVotePollDriveQuery {
-            limit: None,
-            offset: None,
-            start_at: None,
-            vote_poll: ContestedDocumentResourceVotePoll {
-                index_name: index_name.to_string(),
-                index_values: index_values.to_vec(),
-                document_type_name: document_type_name.to_string(),
-                contract_id: contract_id,
-            },
-            allow_include_locked_and_abstaining_vote_tally: true,
-            result_type:
-            ContestedDocumentVotePollDriveQueryResultType::DocumentsAndVoteTally,
-        };
-        let settings = unsafe { (*rust_sdk).get_request_settings() };
-        match ContenderWithSerializedDocument::fetch_many_with_settings(&sdk, query.clone(), settings).await {
-                Ok(contenders) => Ok(contenders),
-                Err(e) => Err(e.to_string())
-        }
+/// Non-blocking version of [get_vote_contenders]
+///
+/// Returns the request id, the result is [RequestResult::Contenders].
+#[ferment_macro::export]
+pub fn get_vote_contenders_async(
+    rust_sdk: * mut DashSdk,
+    index_name: String,
+    index_values: Vec<Value>,
+    document_type_name: String,
+    contract_id: Identifier,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        fetch_vote_contenders(rust_sdk, index_name, index_values, document_type_name, contract_id)
+            .await
+            .map(RequestResult::Contenders)
     })
 }
+
+async fn fetch_vote_contenders(
+    rust_sdk: SdkPtr,
+    index_name: String,
+    index_values: Vec<Value>,
+    document_type_name: String,
+    contract_id: Identifier
+) -> Result<Contenders, PlatformError> {
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+
+    let query = ContestedDocumentVotePollDriveQuery {
+        limit: None,
+        offset: None,
+        start_at: None,
+        vote_poll: ContestedDocumentResourceVotePoll {
+            index_name,
+            index_values,
+            document_type_name,
+            contract_id,
+        },
+        allow_include_locked_and_abstaining_vote_tally: true,
+        result_type:
+        ContestedDocumentVotePollDriveQueryResultType::DocumentsAndVoteTally,
+    };
+    let settings = unsafe { (*rust_sdk.0).get_request_settings() };
+    match ContenderWithSerializedDocument::fetch_many_with_settings(&sdk, query, settings).await {
+        Ok(contenders) => Ok(contenders),
+        Err(e) => Err(e.into())
+    }
+}
+
 #[ferment_macro::export]
 pub fn get_contested_resources(
     rust_sdk: * mut DashSdk,
     document_type_name: String,
     data_contract_id: Identifier
-) -> Result<ContestedResources, String>{
+) -> Result<ContestedResources, PlatformError>{
 
     let rt = unsafe { (*rust_sdk).get_runtime() };
 
     // Execute the async block using the Tokio runtime
-    rt.block_on(async {
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
-
-        tracing::info!("get_contested_resources: starting...");
-        tracing::info!("  sdk: {:?}", sdk);
-        let data_contract = match unsafe { ((*rust_sdk).get_data_contract(&data_contract_id)) } {
-            Some(data_contract) => data_contract.clone(),
-            None => {
-                tracing::info!("Rust SDK doesn't have this contract.  Search platform...");
-                match (DataContract::fetch(&sdk, data_contract_id.clone())
-                    .await) {
-                    Ok(Some(data_contract)) => {
-                        unsafe { (*rust_sdk).add_data_contract(&data_contract); };
-                        Arc::new(data_contract)
-                    },
-                    Ok(None) => return Err("data contract not found".to_string()),
-                    Err(e) => {
-                        tracing::error!("data contract not found {:?}", e);
-                        return Err(e.to_string())
-                    }
-                }
-            }
-        };
-
-        tracing::info!("get_contested_resources: found data contract");
+    rt.block_on(fetch_contested_resources(SdkPtr(rust_sdk), document_type_name, data_contract_id))
+}
 
-        let document_type_result = data_contract
-            .document_type_for_name(&document_type_name);
+/// Non-blocking version of [get_contested_resources]
+///
+/// Returns the request id, the result is [RequestResult::ContestedResources].
+#[ferment_macro::export]
+pub fn get_contested_resources_async(
+    rust_sdk: * mut DashSdk,
+    document_type_name: String,
+    data_contract_id: Identifier,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        fetch_contested_resources(rust_sdk, document_type_name, data_contract_id)
+            .await
+            .map(RequestResult::ContestedResources)
+    })
+}
 
-        let document_type = match document_type_result {
-            Ok(dt) => dt,
-            Err(e) => return Err(e.to_string())
+async fn fetch_contested_resources(
+    rust_sdk: SdkPtr,
+    document_type_name: String,
+    data_contract_id: Identifier
+) -> Result<ContestedResources, PlatformError> {
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+
+    tracing::info!("get_contested_resources: starting...");
+    tracing::info!("  sdk: {:?}", sdk);
+    let data_contract = unsafe { (*rust_sdk.0).get_or_fetch_data_contract(&data_contract_id) }.await?;
+
+    tracing::info!("get_contested_resources: found data contract");
+
+    let document_type = data_contract
+        .document_type_for_name(&document_type_name)?;
+
+    if let Some(contested_index) = document_type.find_contested_index() {
+        let query = VotePollsByDocumentTypeQuery {
+            contract_id: data_contract.id(),
+            document_type_name: document_type.name().to_string(),
+            index_name: contested_index.name.clone(),
+            start_at_value: None,
+            start_index_values: vec!["dash".into()], // hardcoded for dpns
+            end_index_values: vec![],
+            limit: None,
+            order_ascending: true,
         };
 
-        if let Some(contested_index) = document_type.find_contested_index() {
-            let query = VotePollsByDocumentTypeQuery {
-                contract_id: data_contract.id(),
-                document_type_name: document_type.name().to_string(),
-                index_name: contested_index.name.clone(),
-                start_at_value: None,
-                start_index_values: vec!["dash".into()], // hardcoded for dpns
-                end_index_values: vec![],
-                limit: None,
-                order_ascending: true,
-            };
-
-            tracing::info!("get_contested_resources: query ContestedResources for {:?}", query);
-            let settings = unsafe { (*rust_sdk).get_request_settings() };
-            let contested_resources = ContestedResource::fetch_many_with_settings(&sdk, query, settings).await;
-
-            match contested_resources {
-                Ok(resources) => Ok(resources),
-                Err(e) => Err(e.to_string())
-            }
-        } else {
-            return Err("cannot find contested index".to_string());
+        tracing::info!("get_contested_resources: query ContestedResources for {:?}", query);
+        let settings = unsafe { (*rust_sdk.0).get_request_settings() };
+        match ContestedResource::fetch_many_with_settings(&sdk, query, settings).await {
+            Ok(resources) => Ok(resources),
+            Err(e) => Err(e.into())
         }
-    })
+    } else {
+        Err(PlatformError::InvalidInput(format!("cannot find contested index in {}", document_type_name)))
+    }
 }
 
 #[test]
@@ -342,30 +408,53 @@ fn get_vote_contenders_test() {
 pub fn get_votes(
     rust_sdk: * mut DashSdk,
     data_contract_id: Identifier
-) -> Result<Option<Vote>, String>{
+) -> Result<Option<Vote>, PlatformError>{
 
     let rt = unsafe { (*rust_sdk).get_runtime() }.clone();
 
     // Execute the async block using the Tokio runtime
-    rt.block_on(async {
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
-        let settings = unsafe { (*rust_sdk).get_request_settings() };
-
-        let query = ContestedResourceVotesGivenByIdentityQuery {
-            identity_id: data_contract_id, //Identifier::from_string("", Encoding::Base58).unwrap(),
-            offset: None,
-            limit: None,
-            start_at: None,
-            order_ascending: false,
-        };
+    rt.block_on(fetch_votes(SdkPtr(rust_sdk), data_contract_id))
+}
 
-        match Vote::fetch_with_settings(&sdk, query.clone(), settings).await {
-            Ok(vote) => Ok(vote),
-            Err(e) => Err(e.to_string())
-        }
+/// Non-blocking version of [get_votes]
+///
+/// Returns the request id, the result is [RequestResult::OptionalVote].
+#[ferment_macro::export]
+pub fn get_votes_async(
+    rust_sdk: * mut DashSdk,
+    data_contract_id: Identifier,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        fetch_votes(rust_sdk, data_contract_id)
+            .await
+            .map(RequestResult::OptionalVote)
     })
 }
 
+async fn fetch_votes(
+    rust_sdk: SdkPtr,
+    data_contract_id: Identifier
+) -> Result<Option<Vote>, PlatformError> {
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    let settings = unsafe { (*rust_sdk.0).get_request_settings() };
+
+    let query = ContestedResourceVotesGivenByIdentityQuery {
+        identity_id: data_contract_id, //Identifier::from_string("", Encoding::Base58).unwrap(),
+        offset: None,
+        limit: None,
+        start_at: None,
+        order_ascending: false,
+    };
+
+    match Vote::fetch_with_settings(&sdk, query, settings).await {
+        Ok(vote) => Ok(vote),
+        Err(e) => Err(e.into())
+    }
+}
+
 #[test]
 fn get_votes_test() {
     let mut sdk = create_dash_sdk_using_core_testnet();
@@ -388,29 +477,58 @@ pub fn get_votepolls(
     start_time_included: bool,
     end_time: TimestampMillis,
     end_time_included: bool
-) -> Result<VotePollsGroupedByTimestamp, String>{
+) -> Result<VotePollsGroupedByTimestamp, PlatformError>{
 
     let rt = unsafe { (*rust_sdk).get_runtime() }.clone();
 
-    rt.block_on(async {
-        let sdk = unsafe { (*rust_sdk).get_sdk() };
-        let settings = unsafe { (*rust_sdk).get_request_settings() };
-
-        let query = VotePollsByEndDateDriveQuery {
-            start_time: Some((start_time, start_time_included)),
-            end_time: Some((end_time, end_time_included)),
-            limit: None,
-            offset: None,
-            order_ascending: true,
-        };
+    rt.block_on(fetch_votepolls(SdkPtr(rust_sdk), start_time, start_time_included, end_time, end_time_included))
+}
 
-        match VotePoll::fetch_many_with_settings(&sdk, query.clone(), settings).await {
-            Ok(votes) => Ok(votes),
-            Err(e) => Err(e.to_string())
-        }
+/// Non-blocking version of [get_votepolls]
+///
+/// Returns the request id, the result is [RequestResult::VotePolls].
+//#[ferment_macro::export]
+pub fn get_votepolls_async(
+    rust_sdk: * mut DashSdk,
+    start_time: TimestampMillis,
+    start_time_included: bool,
+    end_time: TimestampMillis,
+    end_time_included: bool,
+    completion_context: usize,
+    completion_callback: u64
+) -> u64 {
+    let rust_sdk = SdkPtr(rust_sdk);
+    spawn_request(rust_sdk, completion_context, completion_callback, async move {
+        fetch_votepolls(rust_sdk, start_time, start_time_included, end_time, end_time_included)
+            .await
+            .map(RequestResult::VotePolls)
     })
 }
 
+async fn fetch_votepolls(
+    rust_sdk: SdkPtr,
+    start_time: TimestampMillis,
+    start_time_included: bool,
+    end_time: TimestampMillis,
+    end_time_included: bool
+) -> Result<VotePollsGroupedByTimestamp, PlatformError> {
+    let sdk = unsafe { (*rust_sdk.0).get_sdk() };
+    let settings = unsafe { (*rust_sdk.0).get_request_settings() };
+
+    let query = VotePollsByEndDateDriveQuery {
+        start_time: Some((start_time, start_time_included)),
+        end_time: Some((end_time, end_time_included)),
+        limit: None,
+        offset: None,
+        order_ascending: true,
+    };
+
+    match VotePoll::fetch_many_with_settings(&sdk, query, settings).await {
+        Ok(votes) => Ok(votes),
+        Err(e) => Err(e.into())
+    }
+}
+
 #[test]
 fn get_votepolls_test() {
     let mut sdk = create_dash_sdk_using_core_testnet();
@@ -443,7 +561,7 @@ pub fn get_last_vote_from_masternode(
     index_values: Vec<Value>,
     document_type_name: String,
     contract_id: Identifier
-) -> Result<ResourceVotesByIdentity, String>{
+) -> Result<ResourceVotesByIdentity, PlatformError>{
 
     let rt = unsafe { (*rust_sdk).get_runtime() }.clone();
 
@@ -458,12 +576,12 @@ pub fn get_last_vote_from_masternode(
                 document_type_name,
                 index_name,
                 index_values,
-            }.unique_id().unwrap(),
+            }.unique_id()?,
         };
 
         match ResourceVote::fetch_many_with_settings(&sdk, query.clone(), settings).await {
             Ok(votes) => Ok(votes),
-            Err(e) => Err(e.to_string())
+            Err(e) => Err(e.into())
         }
     })
 }